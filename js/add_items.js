@@ -1,24 +1,27 @@
-function add_item(item) {
-  // return window.wasmBindings.add_item(item);
-  const data_element = document.getElementById("extra-data-div");
-  //let data = JSON.stringify(item, (k, v) => { return v === undefined ? "\\000" : v; }); // Convert to JSON and prevent undefined values
-  // Need to do it in this weird way, because I specifically want it to be undefined. Null won't work
-  //data = data.replace(/"\\\\000"/g, "undefined");
-  const data = JSON.stringify(item, (k, v) => { return v === undefined ? null : v; });
-  console.log(`Data: ${data}`);
-  let new_data = data_element.getAttribute("data-extra-data");
-  // Check if new_data is empty, or, if it's not, ends with a comma
-  if (new_data !== "" && new_data != null) {
-    if (!new_data.endsWith(",")) {
-      new_data += ",";
-    }
-  } else {
-    new_data = "";
-  }
+// surface is the name of the surface to add the item to. Defaults to the one being edited
+// Returns the id of the new item, which can be used to update or remove it
+function add_item(item, surface = undefined) {
+  return defaultEditor().add_item(item, surface);
+}
+
+// Changes some of an item's fields, e.g. update_item(id, { x: 10, text: { font_size: 20 } })
+// Template items can only be changed the way their role allows, and throw otherwise. Set force to
+// change them anyway
+function update_item(id, changes, force = false) {
+  defaultEditor().update_item(id, changes, force);
+}
 
-  new_data += data;
-  data_element.setAttribute("data-extra-data", new_data);
+// Only free items can be removed, unless force is set
+function remove_item(id, force = false) {
+  defaultEditor().remove_item(id, force);
+}
+
+// Gets an item, including its image data
+function get_item(id) {
+  return defaultEditor().get_item(id);
+}
 
-  // Press the update button
-  document.getElementById("canvas-update-trigger").click();
+// Gets every item on a surface, or the one being edited. The first item is on top
+function get_items(surface = undefined) {
+  return defaultEditor().get_items(surface);
 }
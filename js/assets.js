@@ -0,0 +1,11 @@
+// Lets images in a design refer to an asset instead of holding the image, e.g.
+// add_item({ file: { name: "photo.jpg", source: "asset-123" } })
+// Saved designs only keep the source, and the image is loaded from it whenever the design is opened
+
+// Sets how images are loaded from their source. loader is called with the source, and returns (or
+// resolves to) a Blob, Response, ArrayBuffer or Uint8Array, or a URL to fetch the image from, e.g.
+// set_asset_loader(source => fetch(`/assets/${source}`))
+// Without a loader, sources are fetched as URLs. Images that failed to load are tried again
+function set_asset_loader(loader) {
+  defaultEditor().set_asset_loader(loader);
+}
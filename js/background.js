@@ -0,0 +1,13 @@
+// Sets the background of the canvas. Takes {color, image, transparent_export}, where image is a URL
+// Anything left out isn't changed. surface is the name of the surface to set it on, and defaults
+// to the one being edited
+function set_canvas_background(background, surface = undefined) {
+  defaultEditor().set_canvas_background(background, surface);
+}
+
+// Changes some of the canvas settings, e.g. update_settings({ width: 100, unit: "mm" }). Takes the
+// same fields as the canvas in the editor's configuration, and throws if the result isn't valid.
+// Items keep their geometry, so changing the unit changes their size too
+function update_settings(settings, surface = undefined) {
+  defaultEditor().update_settings(settings, surface);
+}
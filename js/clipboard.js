@@ -0,0 +1,16 @@
+// Used by the editor to copy items. The items are copied as text, along with a PNG of the image
+// when one is given, so it can be pasted into other apps
+function writeClipboard(text, png) {
+  if (png && window.ClipboardItem) {
+    const item = new ClipboardItem({
+      "text/plain": new Blob([text], { type: "text/plain" }),
+      "image/png": new Blob([png], { type: "image/png" }),
+    });
+    // Some browsers can't copy images, so fall back to just the text
+    navigator.clipboard.write([item])
+      .catch(() => navigator.clipboard.writeText(text))
+      .catch(err => console.error("Failed to copy", err));
+    return;
+  }
+  navigator.clipboard.writeText(text).catch(err => console.error("Failed to copy", err));
+}
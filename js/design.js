@@ -0,0 +1,24 @@
+// Gets the saved form of the design, as a list of {name, items} for each surface
+// surface is the name of a single surface to get. Leave it out to get every surface
+function get_design(surface = undefined) {
+  return Promise.resolve(defaultEditor().get_design(surface));
+}
+
+// Replaces the whole design, e.g. with a template. Takes the same list of {name, items} that
+// get_design gives. Surfaces that aren't in it are cleared
+function set_design(design) {
+  defaultEditor().set_design(design);
+}
+
+// Gets everything about the editor at once: {active_surface, selected_item, surfaces, validation},
+// where surfaces is the same as get_design() and validation is the same as validate_design()
+function get_state() {
+  return defaultEditor().get_state();
+}
+
+// Gets a link that opens the editor with the design as it is now, e.g. for "continue editing"
+// links. url is the page to link to, and defaults to the current one
+// Throws if the design is too big for a link, which usually means it has images without a source
+function get_design_link(url = undefined) {
+  return defaultEditor().get_design_link(url);
+}
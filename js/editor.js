@@ -0,0 +1,8 @@
+// The editor the helper functions control. The editor sets this when it starts
+// Errors from the editor are thrown as strings
+function defaultEditor() {
+  if (!window.webImageEditor) {
+    throw new Error("The editor hasn't started yet");
+  }
+  return window.webImageEditor;
+}
@@ -0,0 +1,26 @@
+// Lets the host page know when something happens in the editor
+// Events are dispatched on the element the editor is in as "web-image-editor:<name>", with the data
+// in event.detail, and sent to listeners added with on_editor_event. They bubble up to the document,
+// where event.target says which editor sent them
+
+function dispatchEditorEvent(target, name, detail) {
+  (target ?? document).dispatchEvent(new CustomEvent(`web-image-editor:${name}`, { detail, bubbles: true }));
+}
+
+// Calls callback with the details of every event called name. Events are:
+// change: {type, surface, item_id, item} when an item is added, updated or removed, a surface's
+//   background or other settings change, or the design is replaced. type is "add", "update",
+//   "remove", "background", "settings" or "design"
+// selection: {surface, item_id, item} when the user selects an item. Everything is null when the
+//   selected item goes away
+// item in change and selection events is {id, role, x, y, width, height, text, file}, where file is
+//   {name, width, height, source}. It leaves out the image data, which get_item has
+// validation: the same as validate_design, once the editor starts and whenever the result changes
+// export, export-error: see on_export
+// asset-error: {source, error} when an image can't be loaded from its source (see set_asset_loader)
+// Returns a function that stops the callback being called
+function on_editor_event(name, callback) {
+  const editor = defaultEditor();
+  editor.on(name, callback);
+  return () => editor.off(name, callback);
+}
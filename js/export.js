@@ -0,0 +1,39 @@
+// Exports a surface as an image file, rendered by the editor so it's the same in every browser
+// options is {format, quality, scale, filename, fonts, download}, where format is "png", "jpeg",
+// "webp", "pdf" or "svg" and quality (1-100, default 90) only applies to JPEG. WebP exports are
+// lossless, so their quality is ignored. Anything left out uses the defaults
+// fonts maps font families to the URL of a font file, e.g. { "Arial": "/fonts/arial.ttf" }. PDFs
+// embed these so the text stays as text. Families without a font file use a standard PDF font
+// Image exports draw text with these too, so it looks the same in every browser. Families without
+// a font file are drawn by the browser, and can differ slightly between browsers
+// download saves the file in the browser, and defaults to true. Set it to false to upload the
+// export yourself
+// surface is the name of the surface to export, and defaults to the one being edited
+// Resolves with the export (see on_export)
+function export_design(options = {}, surface = undefined) {
+  return defaultEditor().export(options, surface).then(exportWithBlob);
+}
+
+// Calls callback with every export, including ones from the editor's Save button. Exports are
+// {surface, filename, mime_type, bytes, blob, design}, where bytes is a Uint8Array of the file,
+// blob is the same file as a Blob, and design is the saved form of the surface (see get_design)
+// Returns a function that stops the callback being called
+function on_export(callback) {
+  return on_editor_event("export", detail => callback(exportWithBlob(detail)));
+}
+
+function exportWithBlob(detail) {
+  return { ...detail, blob: new Blob([detail.bytes], { type: detail.mime_type }) };
+}
+
+// Saves bytes made by the editor as a file
+function download_file(bytes, mime_type, filename) {
+  const url = URL.createObjectURL(new Blob([bytes], { type: mime_type }));
+  const a = document.createElement('a');
+  a.href = url;
+  a.download = filename;
+  document.body.appendChild(a);
+  a.click();
+  document.body.removeChild(a);
+  URL.revokeObjectURL(url);
+}
@@ -0,0 +1,7 @@
+// Sets the product mockups the design can be previewed on. Each mockup looks like
+// {name, image, width, height, placement, shading, displacement_map, displacement_scale}
+// where placement is either {type: "rect", x, y, width, height} or {type: "quad", corners: [[x, y], ...]}
+// in pixels of the mockup image
+function set_mockups(mockups) {
+  defaultEditor().set_mockups(mockups);
+}
@@ -6,3 +6,16 @@ let urlParams = new URLSearchParams(window.location.search);
 function getParam(name) {
   return urlParams.getAll(name);
 }
+
+// Returns url with the design= parameter set to design, and without any items given the old way
+function designUrl(url, design) {
+  const itemParams = ["type", "value", "x", "y", "width", "height", "movable", "editable", "font_size", "font_family",
+    "font_color", "background_color", "name", "surface"];
+
+  const result = new URL(url, window.location.href);
+  for (const param of itemParams) {
+    result.searchParams.delete(param);
+  }
+  result.searchParams.set("design", design);
+  return result.toString();
+}
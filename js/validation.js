@@ -0,0 +1,7 @@
+// Functions for the host page to check the design before it's ordered
+
+// Returns every problem found with the design: {valid, print_warnings, safe_zone_warnings,
+// empty_placeholders}. The design is good to go if valid is true
+function validate_design() {
+  return defaultEditor().validate();
+}
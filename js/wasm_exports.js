@@ -20,12 +20,12 @@ function build_file_item({name, file_type, data, x, y, width, height, movable})
   return build_item({text: undefined, file, x, y, width, height, movable});
 }
 
-function add_text_item({text, x, y, width, height, font_size, font_family, font_color, background_color, editable, movable}) {
+function add_text_item({text, x, y, width, height, font_size, font_family, font_color, background_color, editable, movable, surface}) {
   const item = build_text_item({text, x, y, width, height, font_size, font_family, font_color, background_color, editable, movable});
-  return add_item(item);
+  return add_item(item, surface);
 }
 
-function add_file_item({name, file_type, data, x, y, width, height, movable}) {
+function add_file_item({name, file_type, data, x, y, width, height, movable, surface}) {
   const item = build_file_item({name, file_type, data, x, y, width, height, movable});
-  return add_item(item);
+  return add_item(item, surface);
 }
@@ -4,88 +4,321 @@ mod editable_canvas_div;
 mod image_movable_div;
 #[path = "image_static_div.rs"]
 mod image_static_div;
+#[path = "image_frame_div.rs"]
+mod image_frame_div;
 #[path = "textbox_movable_div.rs"]
 mod textbox_movable_div;
 #[path = "textbox_static_div.rs"]
 mod textbox_static_div;
 #[path = "settings.rs"]
-mod settings;
+pub(crate) mod settings;
+#[path = "print_quality.rs"]
+mod print_quality;
+#[path = "mockup_preview.rs"]
+mod mockup_preview;
+#[path = "surface.rs"]
+mod surface;
+#[path = "export.rs"]
+mod export;
+#[path = "pdf.rs"]
+pub(crate) mod pdf;
+#[path = "font_subset.rs"]
+mod font_subset;
+#[path = "svg.rs"]
+mod svg;
+#[path = "raster.rs"]
+mod raster;
+#[path = "editor_handle.rs"]
+pub mod editor_handle;
+#[path = "editor_events.rs"]
+pub mod editor_events;
+#[path = "iframe_messages.rs"]
+pub mod iframe_messages;
+#[path = "config.rs"]
+pub mod config;
+#[path = "design_link.rs"]
+mod design_link;
+#[path = "assets.rs"]
+pub mod assets;
+#[path = "clipboard.rs"]
+mod clipboard;
+#[path = "selection_focus.rs"]
+mod selection_focus;
 
 use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 use wasm_bindgen::prelude::*;
 use gloo::file::File;
 use gloo::file::callbacks::FileReader;
-use web_sys::{console, HtmlInputElement, FileList};
+use web_sys::{console, DataTransfer, HtmlElement, HtmlInputElement, HtmlSelectElement, FileList};
 use editable_canvas_div::*;
 use image_movable_div::*;
 use image_static_div::*;
+use image_frame_div::*;
 use textbox_movable_div::*;
 use textbox_static_div::*;
 use crate::file_details::*;
 use crate::text_details::*;
 use crate::page_items::*;
-use settings::*;
+use crate::image_cache;
+use crate::mockup::MockupTemplate;
+use mockup_preview::MockupPreview;
+use surface::*;
+use export::*;
+use editor_events::*;
+use config::EditorConfig;
+use settings::{CanvasSettings, QueryError};
+use assets::{AssetLoader, AssetStates, AssetStatus, LoadedAsset};
+
+// How far each paste is moved from the copied item, in pixels, so copies don't hide each other
+const PASTE_OFFSET: i32 = 10;
 
 // Javascript functions
 #[wasm_bindgen]
 extern "C" {
-	#[wasm_bindgen(js_name = capture)]
-	fn capture_div(id: &str);
+	fn download_file(bytes: &[u8], mime_type: &str, filename: &str);
+}
+
+#[derive(Properties, PartialEq, Default)]
+pub struct AppProps {
+	// Where the host page's event listeners are kept
+	#[prop_or_default]
+	pub events: EditorEvents,
+	// Where the host page's asset loader is kept
+	#[prop_or_default]
+	pub assets: AssetLoader,
+	#[prop_or_default]
+	pub config: EditorConfig,
+}
+
+// Background settings sent from the host page. Anything left out is left as it is
+#[derive(Deserialize)]
+pub struct CanvasBackground {
+	#[serde(default)]
+	color: Option<String>,
+	#[serde(default)]
+	image: Option<String>,
+	#[serde(default)]
+	transparent_export: Option<bool>,
+}
+
+// Sent to the host page once an export is done, along with the exported bytes
+#[derive(Serialize)]
+struct ExportResult {
+	surface: String,
+	filename: String,
+	mime_type: String,
+	design: SurfaceDesign,
+}
+
+// Where an uploaded image goes
+#[derive(Clone, Copy)]
+pub enum Placement {
+	// The top left of the canvas, 250 pixels wide
+	Default,
+	// Into a placeholder
+	Frame(u32),
+	// Centered on a point on the canvas, in pixels from its top left. Big images are scaled down to fit
+	At(i32, i32),
 }
 
 pub enum Msg {
-	Loaded(String, String, Vec<u8>, i32, i32),
-	Files(Vec<File>),
+	Loaded(String, String, Vec<u8>, i32, i32, Placement),
+	AssetLoaded(String, Result<LoadedAsset, String>),
+	// Try loading images that failed again, e.g. with a new asset loader
+	ReloadAssets,
+	// Where the files were dropped on the canvas, if they were
+	Files(Vec<File>, Option<(i32, i32)>),
 	Text(TextDetails),
 	Item(PageItems),
-	FinishedLoading,
-	SetupCanvas(CanvasSettings),
+	SurfaceItem(String, PageItems),
+	UpdateItem(PageItems),
+	Remove(u32),
+	Moved(u32, i32, i32, i32, i32),
+	TextEdited(u32, String),
+	Select(Option<u32>),
+	SelectSurface(usize),
+	SetBackground(Option<String>, CanvasBackground),
+	SetSettings(String, CanvasSettings),
+	SetMockups(Vec<MockupTemplate>),
+	SelectMockup(usize),
+	TogglePreview,
+	SetExportFormat(ExportFormat),
+	// Replace every item, e.g. with a template
+	SetDesign(Vec<SurfaceDesign>),
+	SetTextColor(String),
+	Crop(u32, ImageCrop),
+	// Keyboard shortcuts for the selected item
+	Copy,
+	Cut,
+	Duplicate,
+	// Items copied from an editor
+	Paste(Vec<PageItems>),
+	// Text copied from another app
+	PasteText(String),
+	Export(ExportOptions),
 }
 
 pub struct App {
 	readers: HashMap<String, FileReader>,
-	items: Vec<PageItems>,
-	first_load: bool,
-	canvas_settings: CanvasSettings,
+	surfaces: Vec<Surface>,
+	active_surface: usize,
+	mockups: Vec<MockupTemplate>,
+	active_mockup: usize,
+	preview: bool,
+	export_format: ExportFormat,
+	config: EditorConfig,
+	query_errors: Vec<QueryError>,
+	selected: Option<u32>,
+	events: EditorEvents,
+	assets: AssetLoader,
+	// Sources of images being loaded, and why the ones that failed did
+	asset_states: AssetStates,
+	// Pastes since the last copy. Each one is moved further from the copied item
+	pastes: i32,
 }
 
 impl Component for App {
 	type Message = Msg;
-	type Properties = ();
+	type Properties = AppProps;
+
+	fn create(ctx: &Context<Self>) -> Self {
+		let mut config = ctx.props().config.clone();
+		let query_errors = if config.query_override { config.apply_query() } else { Vec::new() };
+		for err in &query_errors {
+			console::error_1(&format!("Query string: {}", err).into());
+		}
+
+		// Surfaces need to be set up before items can be added to them
+		let mut surfaces: Vec<Surface> = config.surface_settings().into_iter()
+			.map(|(name, settings)| Surface::new(name, settings))
+			.collect();
+
+		add_design(&mut surfaces, &config.design);
+		// So the first validation event covers the starting design
+		for surface in surfaces.iter_mut() {
+			surface.refresh_warnings();
+		}
 
-	fn create(_ctx: &Context<Self>) -> Self {
 		Self {
 			readers: HashMap::default(),
-			items: Vec::default(),
-			first_load: true,
-			canvas_settings: CanvasSettingsBuilder::default().build().unwrap(),
+			surfaces,
+			active_surface: 0,
+			mockups: Vec::new(),
+			active_mockup: 0,
+			preview: false,
+			export_format: ExportFormat::Png,
+			config,
+			query_errors,
+			selected: None,
+			events: ctx.props().events.clone(),
+			assets: ctx.props().assets.clone(),
+			asset_states: AssetStates::default(),
+			pastes: 0,
 		}
 	}
 
 	fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
-		match msg {
-			Msg::Loaded(file_name, file_type, data, width, height) => {
+		let rerender = match msg {
+			Msg::Loaded(file_name, file_type, data, width, height, placement) => {
 				let file_details = FileDetails {
 					name: file_name.clone(),
 					file_type,
 					data,
 					width,
 					height,
+					source: None,
 				};
 
-				self.items.push(PageItemsBuilder::default().file(Some(file_details)).width(Some(250)).build().unwrap());
 				self.readers.remove(&file_name);
+				let frame = match placement {
+					Placement::Frame(id) => self.surface_mut().items.iter_mut().find(|item| item.id == id && item.role == ItemRole::Placeholder),
+					_ => None,
+				};
+				if let Some(item) = frame {
+					// A new photo starts out covering the frame
+					item.file = Some(file_details);
+					item.crop = ImageCrop::default();
+					let id = item.id;
+					image_cache::release(id);
+					self.emit_change(ChangeKind::Update, id);
+				} else if self.has_room(self.surface()) {
+					let settings = &self.surface().canvas_settings;
+					let item = match placement {
+						Placement::At(x, y) => {
+							// Scale it down to fit on the canvas, keeping it on the canvas
+							let (canvas_width, canvas_height) = (settings.screen_width(), settings.screen_height());
+							let scale = (canvas_width as f64 / width.max(1) as f64).min(canvas_height as f64 / height.max(1) as f64).min(1.0);
+							let fitted_width = (width as f64 * scale).round() as i32;
+							let fitted_height = (height as f64 * scale).round() as i32;
+							let left = (x - fitted_width / 2).clamp(0, (canvas_width - fitted_width).max(0));
+							let top = (y - fitted_height / 2).clamp(0, (canvas_height - fitted_height).max(0));
+							PageItemsBuilder::default().file(Some(file_details))
+								.x(settings.from_screen(left))
+								.y(settings.from_screen(top))
+								.width(Some(settings.from_screen(fitted_width)))
+								.height(Some(settings.from_screen(fitted_height)))
+								.build().unwrap()
+						}
+						_ => PageItemsBuilder::default().file(Some(file_details)).width(Some(settings.from_screen(250))).build().unwrap(),
+					};
+					let id = item.id;
+					self.surface_mut().items.push(item);
+					self.emit_change(ChangeKind::Add, id);
+				}
 				true
 			}
-			Msg::Files(files) => {
+			Msg::AssetLoaded(source, result) => {
+				match self.asset_states.finish(&source, result) {
+					Ok(image) => {
+						for surface in self.surfaces.iter_mut() {
+							for id in assets::fill_items(&mut surface.items, &surface.canvas_settings, &source, &image) {
+								image_cache::release(id);
+							}
+						}
+					}
+					Err(err) => {
+						console::error_1(&err.clone().into());
+						self.events.emit("asset-error", &AssetErrorEvent { source: &source, error: &err });
+					}
+				}
+				true
+			}
+			Msg::ReloadAssets => {
+				self.asset_states.retry();
+				true
+			}
+			Msg::Files(files, position) => {
+				// Dropped images go where they were dropped. Otherwise the first image goes in the selected
+				// placeholder, if there is one
+				let mut frame = match position {
+					Some(_) => None,
+					None => self.selected.filter(|id| self.surface().items.iter().any(|item| item.id == *id && item.role == ItemRole::Placeholder)),
+				};
 				for file in files.into_iter() {
 					let file_name = file.name();
 					let file_type = file.raw_mime_type();
 
+					// Dropped files can be anything
+					if !file_type.starts_with("image/") {
+						console::error_1(&format!("{} isn't an image", file_name).into());
+						continue;
+					}
+					if self.config.limits.max_file_size.is_some_and(|max_file_size| file.size() > max_file_size) {
+						console::error_1(&format!("{} is too big to upload", file_name).into());
+						self.events.emit("limit", &LimitEvent { limit: "max_file_size", surface: None, file: Some(&file_name) });
+						continue;
+					}
+
 					let task = {
 						let link = ctx.link().clone();
 						let file_name = file_name.clone();
+						let placement = match (frame.take(), position) {
+							(Some(id), _) => Placement::Frame(id),
+							(None, Some((x, y))) => Placement::At(x, y),
+							(None, None) => Placement::Default,
+						};
 
 						gloo::file::callbacks::read_as_bytes(&file, move |res| {
 							// Get width and height of image
@@ -108,6 +341,7 @@ impl Component for App {
 									data,
 									width,
 									height,
+									placement,
 							  ))
 						})
 					};
@@ -116,76 +350,351 @@ impl Component for App {
 				true
 			}
 			Msg::Text(text) => {
-				self.items.push(PageItemsBuilder::default().text(Some(text)).build().unwrap());
+				if self.has_room(self.surface()) {
+					let item = PageItemsBuilder::default().text(Some(text)).build().unwrap();
+					let id = item.id;
+					self.surface_mut().items.push(item);
+					self.emit_change(ChangeKind::Add, id);
+				}
 				true
 			}
 			Msg::Item(item) => {
-				self.items.push(item);
+				if self.has_room(self.surface()) {
+					let id = item.id;
+					self.surface_mut().items.push(item);
+					self.emit_change(ChangeKind::Add, id);
+				}
 				true
 			}
-			Msg::FinishedLoading => {
-				self.first_load = false;
+			Msg::SurfaceItem(name, item) => {
+				let id = item.id;
+				match self.surfaces.iter().position(|surface| surface.name == name) {
+					Some(index) => {
+						if self.has_room(&self.surfaces[index]) {
+							self.surfaces[index].items.push(item);
+							self.emit_change(ChangeKind::Add, id);
+						}
+					}
+					None => console::error_1(&format!("No surface called {}. Item not added", name).into()),
+				}
 				true
 			}
-			Msg::SetupCanvas(settings) => {
-				self.canvas_settings = settings;
+			Msg::UpdateItem(new_item) => {
+				let id = new_item.id;
+				if let Some(surface) = self.surface_with_item_mut(new_item.id) {
+					if let Some(item) = surface.items.iter_mut().find(|item| item.id == new_item.id) {
+						// The cached image is out of date if the file changed
+						if item.file != new_item.file {
+							image_cache::release(new_item.id);
+						}
+						*item = new_item;
+					}
+				}
+				self.emit_change(ChangeKind::Update, id);
 				true
 			}
-		}
-	}
+			Msg::Remove(id) => {
+				let surface = self.surface_with_item_mut(id).map(|surface| {
+					surface.items.retain(|item| item.id != id);
+					surface.name.clone()
+				});
+				image_cache::release(id);
 
-	fn view(&self, ctx: &Context<Self>) -> Html {
-		if self.first_load {
-			let items = parse_query();
-			for item in items {
-				ctx.link().send_message(Msg::Item(item));
-			}
-
-			// Parse settings query
-			ctx.link().send_message(Msg::SetupCanvas(parse_settings_query()));
-			ctx.link().send_message(Msg::FinishedLoading);
-		}
-
-		// Check if we have any new items to add to the page
-		match web_sys::window().unwrap().document().unwrap().get_element_by_id("extra-data-div") {
-			Some(extra_data_div) => {
-				match extra_data_div.get_attribute("data-extra-data") {
-					Some(data_str) => {
-						if data_str != "" {
-							// data_str is a comma separated list of items
-							let data_arr_str = format!("[{}]", data_str);
-							console::log_1(&data_arr_str.clone().into());
-							let data: Vec<PageItems> = serde_json::from_str(&data_arr_str).unwrap();
-							for item in data {
-								ctx.link().send_message(Msg::Item(item));
-							}
-							extra_data_div.set_attribute("data-extra-data", "").unwrap();
+				if let Some(surface) = surface {
+					self.events.emit("change", &ChangeEvent { kind: ChangeKind::Remove, surface: &surface, item_id: Some(id), item: None });
+				}
+				if self.selected == Some(id) {
+					self.selected = None;
+					self.emit_selection();
+				}
+				true
+			}
+			Msg::Moved(id, x, y, width, height) => {
+				// The components work in screen pixels, but items are stored in canvas units
+				if let Some(surface) = self.surface_with_item_mut(id) {
+					let settings = &surface.canvas_settings;
+					if let Some(item) = surface.items.iter_mut().find(|item| item.id == id) {
+						item.x = settings.from_screen(x);
+						item.y = settings.from_screen(y);
+						item.width = Some(settings.from_screen(width));
+						item.height = Some(settings.from_screen(height));
+					}
+				}
+				self.emit_change(ChangeKind::Update, id);
+				true
+			}
+			Msg::TextEdited(id, value) => {
+				if let Some(surface) = self.surface_with_item_mut(id) {
+					if let Some(text) = surface.items.iter_mut().find(|item| item.id == id).and_then(|item| item.text.as_mut()) {
+						text.text = value;
+					}
+				}
+				self.emit_change(ChangeKind::Update, id);
+				// The text component already shows the new text
+				false
+			}
+			Msg::Select(id) => {
+				if self.selected != id {
+					self.selected = id;
+					self.emit_selection();
+					// Items show when they're selected, and the palette is only shown while text is
+					true
+				} else {
+					false
+				}
+			}
+			Msg::SelectSurface(index) => {
+				if index < self.surfaces.len() && index != self.active_surface {
+					self.active_surface = index;
+					// The selected item isn't shown any more
+					if self.selected.take().is_some() {
+						self.emit_selection();
+					}
+				}
+				true
+			}
+			Msg::SetBackground(surface, background) => {
+				let surface = match surface {
+					Some(name) => self.surfaces.iter_mut().find(|surface| surface.name == name),
+					None => Some(&mut self.surfaces[self.active_surface]),
+				};
+
+				let surface = match surface {
+					Some(surface) => {
+						let settings = &mut surface.canvas_settings;
+						if background.color.is_some() {
+							settings.background_color = background.color;
+						}
+						if background.image.is_some() {
+							settings.background_image = background.image;
+						}
+						if let Some(transparent_export) = background.transparent_export {
+							settings.transparent_export = transparent_export;
+						}
+						Some(surface.name.clone())
+					}
+					None => {
+						console::error_1(&"No surface with that name. Background not set".into());
+						None
+					}
+				};
+
+				if let Some(surface) = surface {
+					self.events.emit("change", &ChangeEvent { kind: ChangeKind::Background, surface: &surface, item_id: None, item: None });
+				}
+				true
+			}
+			Msg::SetSettings(name, settings) => {
+				match self.surfaces.iter_mut().find(|surface| surface.name == name) {
+					Some(surface) => {
+						surface.canvas_settings = settings;
+						self.events.emit("change", &ChangeEvent { kind: ChangeKind::Settings, surface: &name, item_id: None, item: None });
+					}
+					None => console::error_1(&"No surface with that name. Settings not changed".into()),
+				}
+				true
+			}
+			Msg::SetMockups(mockups) => {
+				self.mockups = mockups;
+				self.active_mockup = 0;
+				// Can't preview without a mockup
+				if self.mockups.is_empty() {
+					self.preview = false;
+				}
+				true
+			}
+			Msg::SelectMockup(index) => {
+				if index < self.mockups.len() {
+					self.active_mockup = index;
+				}
+				true
+			}
+			Msg::TogglePreview => {
+				self.preview = !self.preview && !self.mockups.is_empty();
+				true
+			}
+			Msg::SetExportFormat(format) => {
+				self.export_format = format;
+				false
+			}
+			Msg::SetTextColor(color) => {
+				if let Some(id) = self.selected {
+					if let Some(surface) = self.surface_with_item_mut(id) {
+						if let Some(text) = surface.items.iter_mut().find(|item| item.id == id).and_then(|item| item.text.as_mut()) {
+							text.font_color = color;
 						}
 					}
-					None => { console::log_1(&"No extra data".into()); }
+					self.emit_change(ChangeKind::Update, id);
 				}
+				true
+			}
+			Msg::Crop(id, crop) => {
+				if let Some(item) = self.surface_mut().items.iter_mut().find(|item| item.id == id) {
+					item.crop = crop;
+					self.emit_change(ChangeKind::Update, id);
+				}
+				true
 			}
-			None => { console::log_1(&"No extra data div".into()); }
+			Msg::Copy => {
+				if let Some(item) = self.selected_item().filter(|item| item.is_movable()) {
+					clipboard::copy_items(std::slice::from_ref(item));
+					self.pastes = 0;
+				}
+				false
+			}
+			Msg::Cut => {
+				if let Some(item) = self.selected_item().filter(|item| item.is_movable()) {
+					clipboard::copy_items(std::slice::from_ref(item));
+					ctx.link().send_message(Msg::Remove(item.id));
+					self.pastes = 0;
+				}
+				false
+			}
+			Msg::Duplicate => {
+				match self.selected_item().filter(|item| item.is_movable()).cloned() {
+					Some(item) => {
+						self.pastes = 0;
+						self.paste_items(vec![item])
+					}
+					None => false,
+				}
+			}
+			Msg::Paste(items) => self.paste_items(items),
+			Msg::PasteText(text) => {
+				let text = TextDetailsBuilder::default().text(text).font_size(16).build().unwrap();
+				let item = PageItemsBuilder::default().text(Some(text)).build().unwrap();
+				self.paste_items(vec![item])
+			}
+			Msg::SetDesign(design) => {
+				for surface in self.surfaces.iter_mut() {
+					for item in surface.items.drain(..) {
+						image_cache::release(item.id);
+					}
+				}
+				add_design(&mut self.surfaces, &design);
+
+				if self.selected.take().is_some() {
+					self.emit_selection();
+				}
+				for surface in &self.surfaces {
+					self.events.emit("change", &ChangeEvent { kind: ChangeKind::Design, surface: &surface.name, item_id: None, item: None });
+				}
+				true
+			}
+			Msg::Export(options) => {
+				// Rendering loads images, so it has to happen in the background
+				let surface = self.surface().clone();
+				let options = self.with_fonts(options);
+				let events = self.events.clone();
+				wasm_bindgen_futures::spawn_local(async move {
+					let _ = export(surface, options, events).await;
+				});
+				false
+			}
+		};
+
+		self.load_assets(ctx);
+		self.refresh_print_warnings();
+		rerender
+	}
+
+	fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+		// Later validation events only happen when something changes, so the host page needs a starting point
+		if first_render {
+			self.load_assets(ctx);
+			self.events.emit("validation", &Validation::new(&self.surfaces));
+			if !self.query_errors.is_empty() {
+				self.events.emit("query-errors", &QueryErrorsEvent { refused: self.load_refused(), errors: &self.query_errors });
+			}
+		}
+	}
+
+	fn view(&self, ctx: &Context<Self>) -> Html {
+		let surface = self.surface();
+		let settings = &surface.canvas_settings;
+		let mask = settings.mask_url();
+		let export_format = self.export_format;
+		let download_on_save = self.config.save.download;
+		let tools = &self.config.tools;
+		// Files can only be dropped or pasted on the canvas if they could be uploaded
+		let upload = tools.upload;
+		let strings = &self.config.strings;
+		let new_text = strings.new_text.clone();
+
+		let onkeydown = {
+			let (next, previous, selected) = (self.tab_target(true), self.tab_target(false), self.selected.is_some());
+			ctx.link().batch_callback(move |event| Self::shortcut(event, next, previous, selected))
+		};
+
+		if self.load_refused() {
+			return self.view_query_errors();
 		}
 
 		html! {
-			<div>
-				<div id="extra-data-div" style="display: none;"><button id="canvas-update-trigger" onclick={ctx.link().callback(|_| {
-					// Trigger page redraw
-					Msg::FinishedLoading
-				})}>{"Update"}</button></div> // Used to store new items as
-																						 // JsValues
-				<input id="file-upload" type="file" accept="image/*" multiple={true}
-					onchange={ctx.link().callback(move |e: Event| {
-						let input: HtmlInputElement = e.target_unchecked_into();
-						Self::upload_files(input.files())
-				})} />
-					<button id="add-text-button" onclick={ctx.link().callback(|_| {
-						Self::add_text(TextDetailsBuilder::default().text("Hello, World!".to_string()).font_size(16).build().unwrap())
-					})}>{"Add Text"}</button>
-				<button onclick={|_| capture_div("#photo-canvas")} id="save-button">{"Save"}</button>
-				<EditableCanvas id="photo-canvas" width={self.canvas_settings.width} height={self.canvas_settings.height}>
-					{ for self.items.iter().rev().map(App::view_item) }
+			<div class="web-image-editor" lang={self.config.locale.clone()}>
+				if !self.config.fonts.is_empty() {
+					<style>{ self.config.font_faces() }</style>
+				}
+				if tools.upload {
+					<input id={Self::element_id(ctx, "file-upload")} class="file-upload" type="file" accept="image/*" multiple={true}
+						onchange={ctx.link().callback(move |e: Event| {
+							let input: HtmlInputElement = e.target_unchecked_into();
+							Self::upload_files(input.files(), None)
+					})} />
+				}
+				if tools.add_text {
+					<button id={Self::element_id(ctx, "add-text-button")} class="add-text-button" onclick={ctx.link().callback(move |_| {
+						Self::add_text(TextDetailsBuilder::default().text(new_text.clone()).font_size(16).build().unwrap())
+					})}>{ strings.add_text.clone() }</button>
+				}
+				if tools.export_format {
+					<select id={Self::element_id(ctx, "export-format")} class="export-format" onchange={ctx.link().batch_callback(|e: Event| {
+						let select: HtmlSelectElement = e.target_unchecked_into();
+						ExportFormat::parse(&select.value()).map(Msg::SetExportFormat)
+					})}>
+						<option value="png" selected={export_format == ExportFormat::Png}>{"PNG"}</option>
+						<option value="jpeg" selected={export_format == ExportFormat::Jpeg}>{"JPEG"}</option>
+						<option value="webp" selected={export_format == ExportFormat::Webp}>{"WebP"}</option>
+						<option value="pdf" selected={export_format == ExportFormat::Pdf}>{"PDF"}</option>
+						<option value="svg" selected={export_format == ExportFormat::Svg}>{"SVG"}</option>
+					</select>
+				}
+				if self.config.save.show_button {
+					<button onclick={ctx.link().callback(move |_| {
+						Msg::Export(ExportOptions { format: export_format, download: download_on_save, ..ExportOptions::default() })
+					})} id={Self::element_id(ctx, "save-button")} class="save-button">{ strings.save.clone() }</button>
+				}
+				{ self.view_palette(ctx) }
+				if self.surfaces.len() > 1 {
+					<div id={Self::element_id(ctx, "surface-tabs")} class="surface-tabs">
+						{ for self.surfaces.iter().enumerate().map(|(index, surface)| html! {
+							<button class={classes!("surface-tab", (index == self.active_surface).then_some("selected"))}
+								onclick={ctx.link().callback(move |_| Msg::SelectSurface(index))}>
+								{ surface.name.clone() }
+							</button>
+						}) }
+					</div>
+				}
+				if tools.preview && !self.mockups.is_empty() {
+					<button id={Self::element_id(ctx, "preview-button")} class="preview-button" onclick={ctx.link().callback(|_| Msg::TogglePreview)}>
+						{ if self.preview { strings.edit.clone() } else { strings.preview.clone() } }
+					</button>
+				}
+				if self.preview {
+					{ self.view_preview(ctx) }
+				}
+				// Kept around while previewing so the items don't lose their state
+				<EditableCanvas key={surface.name.clone()} id={Self::element_id(ctx, "photo-canvas")} class="photo-canvas" width={settings.screen_width()} height={settings.screen_height()}
+					style={if self.preview { "display: none;" } else { "" }}
+					bleed={settings.to_screen(settings.bleed_units())}
+					safe_margin={settings.to_screen(settings.mm_to_units(settings.safe_mm))}
+					mask={mask.map(AttrValue::from)}
+					background_color={settings.background_color.clone().map(AttrValue::from)}
+					background_image={settings.background_image.clone().map(AttrValue::from)}
+					ondropfiles={ctx.link().batch_callback(move |(files, x, y)| upload.then(|| Self::upload_files(Some(files), Some((x, y)))))}
+					onpaste={ctx.link().batch_callback(move |data| Self::paste(data, upload))}
+					{onkeydown}>
+					{ for surface.items.iter().rev().map(|item| self.view_item(ctx, item)) }
 				</EditableCanvas>
 			</div>
 		}
@@ -193,37 +702,423 @@ impl Component for App {
 }
 
 impl App {
-	fn view_file(file: &FileDetails, width: Option<i32>, height: Option<i32>, start_x: i32, start_y: i32, movable: bool) -> Html {
+	fn view_file(ctx: &Context<Self>, item_id: u32, file: &FileDetails, status: AssetStatus, width: Option<i32>, height: Option<i32>, start_x: i32, start_y: i32, movable: bool, selected: bool, badges: Html) -> Html {
+		let strings = &ctx.props().config.strings;
+		let status_text = match status {
+			AssetStatus::Ready => AttrValue::default(),
+			AssetStatus::Loading => strings.image_loading.clone().into(),
+			AssetStatus::Failed(_) => strings.image_failed.clone().into(),
+		};
+		// Placeholders are sized before the image has loaded, so they're replaced once it has
+		let key = format!("{}-{}", item_id, status == AssetStatus::Ready);
+
 		html! {
 			if movable {
-				<MovableImageComponent file={file.clone()} id={ format!("phote-move-{}", file.name.clone()) } class="image" {width} {height} {start_x} {start_y} />
+				<MovableImageComponent {key} {item_id} file={file.clone()} {status} {status_text} {selected} id={ Self::element_id(ctx, &format!("phote-move-{}", file.name)) } class="image" {width} {height} {start_x} {start_y}
+					ondelete={ctx.link().callback(move |_| Msg::Remove(item_id))}
+					onchange={ctx.link().callback(move |(x, y, width, height)| Msg::Moved(item_id, x, y, width, height))}
+					onselect={ctx.link().callback(move |_| Msg::Select(Some(item_id)))}>
+					{ badges }
+				</MovableImageComponent>
 			} else {
-				<Image file={file.clone()} id={ format!("phote-static-{}", file.name.clone()) } class="image" {width} {height} x={start_x} y={start_y} />
+				<Image {key} {item_id} file={file.clone()} {status} id={ Self::element_id(ctx, &format!("phote-static-{}", file.name)) } class="image" {width} {height} x={start_x} y={start_y}>
+					{ badges }
+				</Image>
 			}
 		}
 	}
 
-	fn view_text(text: &TextDetails, width: Option<i32>, height: Option<i32>, start_x: i32, start_y: i32, movable: bool) -> Html {
+	fn view_text(ctx: &Context<Self>, item_id: u32, text: &TextDetails, width: Option<i32>, height: Option<i32>, start_x: i32, start_y: i32, movable: bool, selected: bool, badges: Html) -> Html {
 		html! {
 			if movable {
-				<MovableTextComponent text={text.clone()} id="text-move" class="text" {width} {height} {start_x} {start_y} />
+				<MovableTextComponent key={item_id} text={text.clone()} {selected} id={Self::element_id(ctx, "text-move")} class="text" {width} {height} {start_x} {start_y}
+					ondelete={ctx.link().callback(move |_| Msg::Remove(item_id))}
+					onchange={ctx.link().callback(move |(x, y, width, height)| Msg::Moved(item_id, x, y, width, height))}
+					onselect={ctx.link().callback(move |_| Msg::Select(Some(item_id)))}
+					ontextchange={ctx.link().callback(move |value| Msg::TextEdited(item_id, value))}>
+					{ badges }
+				</MovableTextComponent>
 			} else {
-				<Text text={text.clone()} id="text-static" class="text" {width} {height} x={start_x} y={start_y} />
+				<Text key={item_id} text={text.clone()} id={Self::element_id(ctx, "text-static")} class="text" {width} {height} x={start_x} y={start_y} {selected}
+					onselect={ctx.link().callback(move |_| Msg::Select(Some(item_id)))}
+					ontextchange={ctx.link().callback(move |value| Msg::TextEdited(item_id, value))}>
+					{ badges }
+				</Text>
 			}
 		}
 	}
 
-	fn view_item(item: &PageItems) -> Html {
-		if let Some(file) = &item.file {
-			Self::view_file(file, item.width, item.height, item.x, item.y, item.movable)
+	// The design shown on the selected mockup, with a button to switch to each of the others
+	fn view_preview(&self, ctx: &Context<Self>) -> Html {
+		let mockup = match self.mockups.get(self.active_mockup) {
+			Some(mockup) => mockup.clone(),
+			None => return html! {},
+		};
+
+		html! {
+			<div id={Self::element_id(ctx, "mockup-preview-container")} class="mockup-preview-container">
+				if self.mockups.len() > 1 {
+					<div id={Self::element_id(ctx, "mockup-select")} class="mockup-select">
+						{ for self.mockups.iter().enumerate().map(|(index, mockup)| html! {
+							<button class={classes!("mockup-select-button", (index == self.active_mockup).then_some("selected"))}
+								onclick={ctx.link().callback(move |_| Msg::SelectMockup(index))}>
+								{ mockup.name.clone() }
+							</button>
+						}) }
+					</div>
+				}
+				<MockupPreview id={Self::element_id(ctx, "mockup-preview")} {mockup} design_width={self.surface().canvas_settings.screen_width()} design_height={self.surface().canvas_settings.screen_height()}>
+					{ for self.surface().items.iter().rev().map(|item| self.view_preview_item(ctx, item)) }
+				</MockupPreview>
+			</div>
+		}
+	}
+
+	// Items in the preview can't be moved, and don't show any badges
+	fn view_preview_item(&self, ctx: &Context<Self>, item: &PageItems) -> Html {
+		let settings = &self.surface().canvas_settings;
+		let x = settings.to_screen(item.x);
+		let y = settings.to_screen(item.y);
+		let width = item.width.map(|width| settings.to_screen(width));
+		let height = item.height.map(|height| settings.to_screen(height));
+
+		if item.role == ItemRole::Placeholder {
+			// Empty placeholders aren't part of the product
+			if item.file.is_some() { self.view_frame(ctx, item, false, html! {}) } else { html! {} }
+		} else if let Some(file) = &item.file {
+			// Static images are laid out in the page's flow, so the preview places them itself
+			html! {
+				<Image key={item.id} item_id={item.id} file={file.clone()} status={self.asset_status(file)} class="image" style="position: absolute;" {width} {height} {x} {y} />
+			}
 		} else if let Some(text) = &item.text {
-			Self::view_text(text, item.width, item.height, item.x, item.y, item.movable)
+			Self::view_text(ctx, item.id, text, width, height, x, y, false, false, html! {})
 		} else {
 			html! {}
 		}
 	}
 
-	fn upload_files(files: Option<FileList>) -> Msg {
+	// Shown instead of the editor in strict mode, so problems with the query string get noticed
+	fn view_query_errors(&self) -> Html {
+		html! {
+			<div class="web-image-editor query-errors">
+				<p>{ self.config.strings.load_failed.clone() }</p>
+				<ul>
+					{ for self.query_errors.iter().map(|err| html! { <li>{ err.to_string() }</li> }) }
+				</ul>
+			</div>
+		}
+	}
+
+	// Colours the selected text can be set to. Template text keeps its colour
+	fn view_palette(&self, ctx: &Context<Self>) -> Html {
+		let text_selected = self.selected.is_some_and(|id| self.surface().items.iter().any(|item| item.id == id && item.text.is_some() && item.is_movable()));
+		if !text_selected || self.config.palette.is_empty() {
+			return html! {};
+		}
+
+		html! {
+			<div id={Self::element_id(ctx, "palette")} class="palette">
+				{ for self.config.palette.iter().map(|color| {
+					let onclick = {
+						let color = color.clone();
+						ctx.link().callback(move |_| Msg::SetTextColor(color.clone()))
+					};
+					html! {
+						<button class="palette-swatch" title={color.clone()} style={format!("background-color: {};", color)} {onclick} />
+					}
+				}) }
+			</div>
+		}
+	}
+
+	// Badges shown on items that won't print well. Ignored by html2canvas so they aren't exported
+	fn view_badges(&self, item: &PageItems) -> Html {
+		let strings = &self.config.strings;
+		let dpi_badge = match self.surface().print_warnings.iter().find(|warning| warning.item_id == item.id) {
+			Some(warning) => html! {
+				<div class="dpi-warning-badge" data-html2canvas-ignore="true" title={strings.low_dpi_warning.replace("{min_dpi}", &format!("{:.0}", warning.min_dpi))}>
+					{ strings.low_dpi_badge.replace("{dpi}", &format!("{:.0}", warning.dpi)) }
+				</div>
+			},
+			None => html! {},
+		};
+
+		let safe_zone_badge = if self.surface().safe_zone_warnings.iter().any(|warning| warning.item_id == item.id) {
+			html! {
+				<div class="safe-zone-warning-badge" data-html2canvas-ignore="true" title={strings.outside_safe_area_warning.clone()}>
+					{ strings.outside_safe_area_badge.clone() }
+				</div>
+			}
+		} else {
+			html! {}
+		};
+
+		html! {
+			<>
+				{ dpi_badge }
+				{ safe_zone_badge }
+			</>
+		}
+	}
+
+	fn view_item(&self, ctx: &Context<Self>, item: &PageItems) -> Html {
+		// Components are positioned in screen pixels
+		let settings = &self.surface().canvas_settings;
+		let x = settings.to_screen(item.x);
+		let y = settings.to_screen(item.y);
+		let width = item.width.map(|width| settings.to_screen(width));
+		let height = item.height.map(|height| settings.to_screen(height));
+
+		let badges = self.view_badges(item);
+		let selected = self.selected == Some(item.id);
+
+		if item.role == ItemRole::Placeholder {
+			self.view_frame(ctx, item, true, badges)
+		} else if let Some(file) = &item.file {
+			Self::view_file(ctx, item.id, file, self.asset_status(file), width, height, x, y, item.is_movable(), selected, badges)
+		} else if let Some(text) = &item.text {
+			// Templates decide whether the text can be edited
+			let text = match item.role {
+				ItemRole::Locked => TextDetails { editable: false, ..text.clone() },
+				ItemRole::TextOnly => TextDetails { editable: true, ..text.clone() },
+				ItemRole::Free | ItemRole::Placeholder => text.clone(),
+			};
+			Self::view_text(ctx, item.id, &text, width, height, x, y, item.is_movable(), selected, badges)
+		} else {
+			html! {}
+		}
+	}
+
+	// A template frame for the customer's photo. Selecting it makes uploads go in it, and files can
+	// be dropped on it
+	fn view_frame(&self, ctx: &Context<Self>, item: &PageItems, editable: bool, badges: Html) -> Html {
+		let settings = &self.surface().canvas_settings;
+		let area = print_quality::item_area(item, settings);
+		let item_id = item.id;
+
+		let strings = &self.config.strings;
+		let status = item.file.as_ref().map(|file| self.asset_status(file)).unwrap_or(AssetStatus::Ready);
+		let selected = editable && self.selected == Some(item_id);
+		let upload = self.config.tools.upload;
+
+		// Enter chooses a photo with the upload button, which puts it in the selected placeholder
+		let upload_id = Self::element_id(ctx, "file-upload");
+		let onedit = Callback::from(move |_| {
+			let upload = web_sys::window().and_then(|window| window.document()).and_then(|document| document.get_element_by_id(&upload_id));
+			if let Some(upload) = upload.and_then(|upload| upload.dyn_into::<HtmlElement>().ok()) {
+				upload.click();
+			}
+		});
+		let text = match (&item.file, &status) {
+			(None, _) => strings.placeholder.clone(),
+			(Some(_), AssetStatus::Loading) => strings.image_loading.clone(),
+			(Some(_), AssetStatus::Failed(_)) => strings.image_failed.clone(),
+			(Some(_), AssetStatus::Ready) => String::new(),
+		};
+
+		html! {
+			<ImageFrameComponent key={item_id} {item_id} id={Self::element_id(ctx, &format!("placeholder-{}", item_id))}
+				class={classes!("placeholder", selected.then_some("selected"))}
+				x={settings.to_screen(area.x)} y={settings.to_screen(area.y)} width={settings.to_screen(area.width)} height={settings.to_screen(area.height)}
+				file={item.file.clone()} crop={item.crop} {status} {text} {editable} {selected} {onedit}
+				onselect={ctx.link().callback(move |_| Msg::Select(Some(item_id)))}
+				oncropchange={ctx.link().callback(move |crop| Msg::Crop(item_id, crop))}
+				ondropfiles={ctx.link().batch_callback(move |files| if upload { vec![Msg::Select(Some(item_id)), Self::upload_files(Some(files), None)] } else { Vec::new() })}>
+				{ badges }
+			</ImageFrameComponent>
+		}
+	}
+
+	// Ids are namespaced with the editor's prefix. Elements have a class of the same name for styling
+	fn element_id(ctx: &Context<Self>, name: &str) -> String {
+		format!("{}{}", ctx.props().config.id_prefix.as_deref().unwrap_or_default(), name)
+	}
+
+	fn surface(&self) -> &Surface {
+		&self.surfaces[self.active_surface]
+	}
+
+	fn surface_mut(&mut self) -> &mut Surface {
+		&mut self.surfaces[self.active_surface]
+	}
+
+	// Find the surface an item is on
+	fn surface_with_item_mut(&mut self, id: u32) -> Option<&mut Surface> {
+		self.surfaces.iter_mut().find(|surface| surface.items.iter().any(|item| item.id == id))
+	}
+
+	// Recheck how every item will print, and let the host page know if anything changed
+	fn refresh_print_warnings(&mut self) {
+		let mut changed = false;
+		for surface in self.surfaces.iter_mut() {
+			changed |= surface.refresh_warnings();
+		}
+
+		if changed {
+			let validation = Validation::new(&self.surfaces);
+			self.events.emit("print-warnings", &validation.print_warnings);
+			self.events.emit("safe-zone-warnings", &validation.safe_zone_warnings);
+			self.events.emit("validation", &validation);
+		}
+	}
+
+	fn asset_status(&self, file: &FileDetails) -> AssetStatus {
+		self.asset_states.status(file)
+	}
+
+	// Start loading images from sources that haven't been loaded yet. Ones that failed aren't tried
+	// again until ReloadAssets
+	fn load_assets(&mut self, ctx: &Context<Self>) {
+		let sources = self.asset_states.start(self.surfaces.iter().flat_map(|surface| surface.items.iter()));
+		for source in sources {
+			let assets = self.assets.clone();
+			let link = ctx.link().clone();
+			wasm_bindgen_futures::spawn_local(async move {
+				let result = assets.load(source.clone()).await;
+				link.send_message(Msg::AssetLoaded(source, result));
+			});
+		}
+	}
+
+	// In strict mode the editor doesn't load if there's anything wrong with the query string
+	fn load_refused(&self) -> bool {
+		self.config.strict_query && !self.query_errors.is_empty()
+	}
+
+	// Whether another item can go on a surface. Lets the host page know if it can't
+	fn has_room(&self, surface: &Surface) -> bool {
+		match self.config.limits.max_items {
+			Some(max_items) if surface.items.len() >= max_items => {
+				console::error_1(&format!("{} already has the most items allowed", surface.name).into());
+				self.events.emit("limit", &LimitEvent { limit: "max_items", surface: Some(&surface.name), file: None });
+				false
+			}
+			_ => true,
+		}
+	}
+
+	// Fonts from the config are used for any the export options leave out
+	fn with_fonts(&self, mut options: ExportOptions) -> ExportOptions {
+		for (family, url) in &self.config.fonts {
+			options.fonts.entry(family.clone()).or_insert_with(|| url.clone());
+		}
+		options
+	}
+
+	// Let the host page know an item was added or changed. Call this once the item is in place
+	fn emit_change(&self, kind: ChangeKind, id: u32) {
+		for surface in &self.surfaces {
+			if let Some(item) = surface.items.iter().find(|item| item.id == id) {
+				self.events.emit("change", &ChangeEvent { kind, surface: &surface.name, item_id: Some(id), item: Some(item.into()) });
+				return;
+			}
+		}
+	}
+
+	fn emit_selection(&self) {
+		let selected = self.selected.and_then(|id| {
+			self.surfaces.iter().find_map(|surface| surface.items.iter().find(|item| item.id == id).map(|item| (surface, item)))
+		});
+		self.events.emit("selection", &SelectionEvent {
+			surface: selected.map(|(surface, _)| surface.name.as_str()),
+			item_id: selected.map(|(_, item)| item.id),
+			item: selected.map(|(_, item)| item.into()),
+		});
+	}
+
+	fn selected_item(&self) -> Option<&PageItems> {
+		self.selected.and_then(|id| self.surface().items.iter().find(|item| item.id == id))
+	}
+
+	// Put copies of items on the surface, moved away from where they were copied from, and select the
+	// last one
+	fn paste_items(&mut self, items: Vec<PageItems>) -> bool {
+		self.pastes += 1;
+		let offset = self.surface().canvas_settings.from_screen(PASTE_OFFSET * self.pastes);
+
+		let mut pasted = None;
+		for item in items {
+			if !self.has_room(self.surface()) {
+				break;
+			}
+			// Copies are the customer's own, even if they were copied from a template
+			let item = PageItems {
+				id: next_item_id(),
+				x: item.x + offset,
+				y: item.y + offset,
+				movable: true,
+				role: ItemRole::Free,
+				..item
+			};
+			let id = item.id;
+			self.surface_mut().items.push(item);
+			self.emit_change(ChangeKind::Add, id);
+			pasted = Some(id);
+		}
+
+		if pasted.is_some() {
+			self.selected = pasted;
+			self.emit_selection();
+		}
+		true
+	}
+
+	// Items copied from an editor are pasted as they were, then images, then text from other apps
+	fn paste(data: DataTransfer, upload: bool) -> Option<Msg> {
+		let text = data.get_data("text/plain").ok().filter(|text| !text.is_empty());
+		if let Some(items) = text.as_deref().and_then(clipboard::parse_items) {
+			return Some(Msg::Paste(items));
+		}
+		// Pasted files are ignored without the upload tool, rather than pasting their names as text
+		if let Some(files) = data.files().filter(|files| files.length() > 0) {
+			return upload.then(|| Self::upload_files(Some(files), None));
+		}
+		text.map(Msg::PasteText)
+	}
+
+	// The item Tab selects next, or Shift+Tab if going backwards. Items are gone through in the order
+	// they're on the page, and there's nothing after the last one so focus can leave the editor. Items
+	// the customer can't change at all are skipped
+	fn tab_target(&self, forward: bool) -> Option<u32> {
+		let mut order: Vec<u32> = self.surface().items.iter().rev().filter(|item| item.is_customizable()).map(|item| item.id).collect();
+		if !forward {
+			order.reverse();
+		}
+
+		match self.selected.and_then(|id| order.iter().position(|other| *other == id)) {
+			Some(index) => order.get(index + 1).copied(),
+			None => order.first().copied(),
+		}
+	}
+
+	// Ctrl+C, Ctrl+X and Ctrl+D copy, cut and duplicate the selected item. Tab and Shift+Tab select the
+	// next and previous items, and Escape deselects. Pasting is handled by the canvas, since it needs
+	// what was pasted, and the items handle moving, resizing and editing themselves
+	fn shortcut(event: KeyboardEvent, next: Option<u32>, previous: Option<u32>, selected: bool) -> Option<Msg> {
+		if clipboard::in_text_field(event.target()) {
+			return None;
+		}
+
+		let msg = if event.ctrl_key() || event.meta_key() {
+			match event.key().to_lowercase().as_str() {
+				"c" => Msg::Copy,
+				"x" => Msg::Cut,
+				"d" => Msg::Duplicate,
+				_ => return None,
+			}
+		} else {
+			match event.key().as_str() {
+				"Tab" => Msg::Select(Some(if event.shift_key() { previous } else { next }?)),
+				"Escape" if selected => Msg::Select(None),
+				_ => return None,
+			}
+		};
+		// Ctrl+D would bookmark the page, and Tab would move focus past the items
+		event.prevent_default();
+		Some(msg)
+	}
+
+	fn upload_files(files: Option<FileList>, position: Option<(i32, i32)>) -> Msg {
 		let mut result = Vec::new();
 
 		if let Some(files) = files {
@@ -234,10 +1129,40 @@ impl App {
 				.map(File::from);
 			result.extend(files);
 		}
-		Msg::Files(result)
+		Msg::Files(result, position)
 	}
 
 	fn add_text(text: TextDetails) -> Msg {
 		Msg::Text(text)
 	}
 }
+
+// Export a surface, downloading it if asked to, and send it to the host page
+// Returns what was sent to the host page
+async fn export(surface: Surface, options: ExportOptions, events: EditorEvents) -> Result<JsValue, String> {
+	let bytes = match export_surface(&surface, &options).await {
+		Ok(bytes) => bytes,
+		Err(err) => {
+			console::error_1(&err.clone().into());
+			events.emit_value("export-error", err.clone().into());
+			return Err(err);
+		}
+	};
+
+	let filename = options.filename_for(&surface);
+	if options.download {
+		download_file(&bytes, options.format.mime_type(), &filename);
+	}
+
+	let result = ExportResult {
+		surface: surface.name.clone(),
+		filename,
+		mime_type: options.format.mime_type().to_string(),
+		design: surface.design(),
+	};
+	let result = serde_wasm_bindgen::to_value(&result).map_err(|err| err.to_string())?;
+	// Sent as a Uint8Array rather than an array of numbers
+	let _ = js_sys::Reflect::set(&result, &"bytes".into(), &js_sys::Uint8Array::from(bytes.as_slice()));
+	events.emit_value("export", result.clone());
+	Ok(result)
+}
@@ -0,0 +1,369 @@
+// Images that are loaded from an asset id or URL instead of being stored in the design
+// The host page can register a loader that's given the id and returns the image, e.g. from its
+// own asset store. Without one, the id is fetched as a URL
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, Response};
+use crate::file_details::FileDetails;
+use crate::page_items::PageItems;
+use super::export::{fetch_bytes, js_error};
+use super::settings::CanvasSettings;
+
+// How far along an image is. Images stored in the design are always ready
+#[derive(PartialEq, Clone, Debug)]
+pub enum AssetStatus {
+	Ready,
+	Loading,
+	Failed(String),
+}
+
+pub struct LoadedAsset {
+	pub data: Vec<u8>,
+	// Worked out from the image itself if the loader doesn't say
+	pub file_type: Option<String>,
+}
+
+// A loaded asset that's been checked to be an image
+pub struct AssetImage {
+	pub data: Vec<u8>,
+	pub file_type: String,
+	pub width: i32,
+	pub height: i32,
+}
+
+impl LoadedAsset {
+	// Check the asset is an image, and work out its size and type
+	pub fn into_image(self, source: &str) -> Result<AssetImage, String> {
+		let size = imagesize::blob_size(&self.data).map_err(|_| format!("{} isn't an image", source))?;
+		let file_type = self.file_type
+			.or_else(|| image::guess_format(&self.data).ok().map(|format| format.to_mime_type().to_string()))
+			.unwrap_or_default();
+		Ok(AssetImage {
+			data: self.data,
+			file_type,
+			width: size.width as i32,
+			height: size.height as i32,
+		})
+	}
+}
+
+// Put an image in every item waiting for it from source. Returns the ids of the items
+pub fn fill_items(items: &mut [PageItems], settings: &CanvasSettings, source: &str, image: &AssetImage) -> Vec<u32> {
+	let mut filled = Vec::new();
+	for item in items.iter_mut() {
+		let Some(file) = item.file.as_mut().filter(|file| file.needs_loading() && file.source.as_deref() == Some(source)) else {
+			continue;
+		};
+		file.data = image.data.clone();
+		file.width = image.width;
+		file.height = image.height;
+		if file.file_type.is_empty() {
+			file.file_type = image.file_type.clone();
+		}
+		// Sized the same as uploads if the design didn't give it a size
+		if item.width.is_none() && item.height.is_none() {
+			item.width = Some(settings.from_screen(250));
+		}
+		filled.push(item.id);
+	}
+	filled
+}
+
+// Which sources are being loaded, and why the ones that failed did
+#[derive(Default)]
+pub struct AssetStates {
+	loading: HashSet<String>,
+	errors: HashMap<String, String>,
+}
+
+impl AssetStates {
+	pub fn status(&self, file: &FileDetails) -> AssetStatus {
+		match &file.source {
+			Some(source) if file.needs_loading() => match self.errors.get(source) {
+				Some(err) => AssetStatus::Failed(err.clone()),
+				None => AssetStatus::Loading,
+			},
+			_ => AssetStatus::Ready,
+		}
+	}
+
+	// Sources the items are waiting for that aren't loading already. Each source is only loaded once,
+	// however many items use it, and ones that failed aren't tried again until retry
+	pub fn start<'a>(&mut self, items: impl Iterator<Item = &'a PageItems>) -> Vec<String> {
+		items
+			.filter_map(|item| item.file.as_ref().filter(|file| file.needs_loading()).and_then(|file| file.source.clone()))
+			.filter(|source| !self.errors.contains_key(source) && self.loading.insert(source.clone()))
+			.collect()
+	}
+
+	// Check what a source loaded is an image, and remember why it isn't if it isn't
+	pub fn finish(&mut self, source: &str, result: Result<LoadedAsset, String>) -> Result<AssetImage, String> {
+		self.loading.remove(source);
+		let image = result.and_then(|asset| asset.into_image(source));
+		if let Err(err) = &image {
+			self.errors.insert(source.to_string(), err.clone());
+		}
+		image
+	}
+
+	pub fn retry(&mut self) {
+		self.errors.clear();
+	}
+}
+
+type LoadFuture = Pin<Box<dyn Future<Output = Result<LoadedAsset, String>>>>;
+
+// Loads a source. The host page's loader is wrapped in one of these
+pub type LoadFn = Rc<dyn Fn(String) -> LoadFuture>;
+
+// The host page's loader, shared between the app and its EditorHandle
+#[derive(Clone, Default)]
+pub struct AssetLoader {
+	loader: Rc<RefCell<Option<LoadFn>>>,
+}
+
+impl PartialEq for AssetLoader {
+	fn eq(&self, other: &Self) -> bool {
+		Rc::ptr_eq(&self.loader, &other.loader)
+	}
+}
+
+impl AssetLoader {
+	// The loader is called with the source and can return, or resolve to, the image as a Blob,
+	// Response, ArrayBuffer or Uint8Array, or a URL to fetch it from
+	pub fn set(&self, loader: Option<js_sys::Function>) {
+		self.set_with(loader.map(|loader| -> LoadFn {
+			Rc::new(move |source| Box::pin(load_with(loader.clone(), source)))
+		}));
+	}
+
+	pub fn set_with(&self, loader: Option<LoadFn>) {
+		*self.loader.borrow_mut() = loader;
+	}
+
+	pub async fn load(&self, source: String) -> Result<LoadedAsset, String> {
+		// Loading starts after the current task, so a loader set straight after the editor starts is used
+		let loader = self.loader.borrow().clone();
+		match loader {
+			Some(loader) => loader(source).await,
+			None => fetch_asset(&source).await,
+		}
+	}
+}
+
+async fn load_with(loader: js_sys::Function, source: String) -> Result<LoadedAsset, String> {
+	let result = loader.call1(&JsValue::NULL, &source.clone().into()).map_err(js_error)?;
+	let value = JsFuture::from(js_sys::Promise::resolve(&result)).await.map_err(js_error)?;
+	read_asset(value).await.map_err(|err| format!("Failed to load {}: {}", source, err))
+}
+
+async fn fetch_asset(url: &str) -> Result<LoadedAsset, String> {
+	Ok(LoadedAsset { data: fetch_bytes(url).await?, file_type: None })
+}
+
+async fn read_asset(value: JsValue) -> Result<LoadedAsset, String> {
+	if let Some(url) = value.as_string() {
+		return fetch_asset(&url).await;
+	}
+
+	let (buffer, file_type) = if let Some(response) = value.dyn_ref::<Response>() {
+		if !response.ok() {
+			return Err(format!("the server responded with {}", response.status()));
+		}
+		(JsFuture::from(response.array_buffer().map_err(js_error)?).await.map_err(js_error)?, None)
+	} else if let Some(blob) = value.dyn_ref::<Blob>() {
+		let file_type = Some(blob.type_()).filter(|file_type| !file_type.is_empty());
+		(JsFuture::from(blob.array_buffer()).await.map_err(js_error)?, file_type)
+	} else if value.is_instance_of::<js_sys::ArrayBuffer>() || value.is_instance_of::<js_sys::Uint8Array>() {
+		(value, None)
+	} else {
+		return Err("the asset loader didn't return an image".to_string());
+	};
+
+	Ok(LoadedAsset { data: js_sys::Uint8Array::new(&buffer).to_vec(), file_type })
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Cursor, Read, Write};
+	use std::net::{TcpListener, TcpStream};
+	use std::pin::pin;
+	use std::sync::Arc;
+	use std::task::{Context, Poll, Wake, Waker};
+	use std::thread;
+	use image::{ImageFormat, RgbImage};
+	use crate::page_items::PageItemsBuilder;
+	use super::super::export::check_images;
+	use super::super::settings::CanvasSettingsBuilder;
+	use super::*;
+
+	// Stands in for the host page's asset server. Answers every request for path with the response,
+	// and everything else with a 404
+	fn serve(responses: Vec<(&'static str, &'static str, Vec<u8>)>) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address = listener.local_addr().unwrap();
+		thread::spawn(move || {
+			for mut stream in listener.incoming().flatten() {
+				let mut request = [0; 1024];
+				let length = stream.read(&mut request).unwrap_or(0);
+				let path = String::from_utf8_lossy(&request[..length]).split_whitespace().nth(1).unwrap_or("").to_string();
+				let (status, content_type, body) = match responses.iter().find(|(served, _, _)| *served == path) {
+					Some((_, content_type, body)) => ("200 OK", *content_type, body.clone()),
+					None => ("404 Not Found", "text/plain", b"Not found".to_vec()),
+				};
+				let header = format!("HTTP/1.0 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n", status, content_type, body.len());
+				let _ = stream.write_all(header.as_bytes());
+				let _ = stream.write_all(&body);
+			}
+		});
+		format!("http://{}", address)
+	}
+
+	// Stands in for the host page's loader: fetch the source and hand back the body with its type
+	fn fetch(url: &str) -> Result<LoadedAsset, String> {
+		let address = url.trim_start_matches("http://");
+		let (host, path) = address.split_at(address.find('/').unwrap());
+		let mut stream = TcpStream::connect(host).map_err(|err| err.to_string())?;
+		write!(stream, "GET {} HTTP/1.0\r\n\r\n", path).map_err(|err| err.to_string())?;
+		let mut response = Vec::new();
+		stream.read_to_end(&mut response).map_err(|err| err.to_string())?;
+
+		let split = response.windows(4).position(|window| window == b"\r\n\r\n").ok_or("No headers")?;
+		let headers = String::from_utf8_lossy(&response[..split]).to_string();
+		let status = headers.split_whitespace().nth(1).unwrap_or("");
+		if status != "200" {
+			return Err(format!("the server responded with {}", status));
+		}
+		let file_type = headers.lines()
+			.find_map(|line| line.strip_prefix("Content-Type: "))
+			.map(str::to_string);
+		Ok(LoadedAsset { data: response[split + 4..].to_vec(), file_type })
+	}
+
+	fn loader() -> AssetLoader {
+		let loader = AssetLoader::default();
+		loader.set_with(Some(Rc::new(|source: String| -> LoadFuture { Box::pin(async move { fetch(&source) }) })));
+		loader
+	}
+
+	// The stand-in loader never waits, so its futures are ready the first time they're polled
+	fn block_on<F: Future>(future: F) -> F::Output {
+		struct NoWake;
+		impl Wake for NoWake {
+			fn wake(self: Arc<Self>) {}
+		}
+
+		let waker = Waker::from(Arc::new(NoWake));
+		let mut context = Context::from_waker(&waker);
+		let mut future = pin!(future);
+		loop {
+			if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+				return output;
+			}
+		}
+	}
+
+	fn png(width: u32, height: u32) -> Vec<u8> {
+		let mut data = Vec::new();
+		RgbImage::new(width, height).write_to(&mut Cursor::new(&mut data), ImageFormat::Png).unwrap();
+		data
+	}
+
+	fn image_item(id: u32, source: &str) -> PageItems {
+		let file = FileDetails {
+			name: format!("photo-{}.png", id),
+			file_type: String::new(),
+			data: Vec::new(),
+			width: 0,
+			height: 0,
+			source: Some(source.to_string()),
+		};
+		PageItemsBuilder::default().id(id).file(Some(file)).build().unwrap()
+	}
+
+	fn status(states: &AssetStates, item: &PageItems) -> AssetStatus {
+		states.status(item.file.as_ref().unwrap())
+	}
+
+	#[test]
+	fn loads_images_from_the_server() {
+		let server = serve(vec![("/photo.png", "image/png", png(4, 2))]);
+		let source = format!("{}/photo.png", server);
+		let settings = CanvasSettingsBuilder::default().build().unwrap();
+		let mut items = vec![image_item(1, &source), image_item(2, &source), image_item(3, "other")];
+		let mut states = AssetStates::default();
+
+		// Each source is loaded once
+		assert_eq!(states.start(items.iter()), [source.clone(), "other".to_string()]);
+		assert_eq!(states.start(items.iter()), Vec::<String>::new());
+		assert_eq!(status(&states, &items[0]), AssetStatus::Loading);
+
+		let image = states.finish(&source, block_on(loader().load(source.clone()))).unwrap();
+		assert_eq!(fill_items(&mut items, &settings, &source, &image), [1, 2]);
+
+		let file = items[0].file.as_ref().unwrap();
+		assert_eq!((file.width, file.height, file.file_type.as_str()), (4, 2, "image/png"));
+		assert_eq!(items[0].width, Some(250.0));
+		assert_eq!(status(&states, &items[0]), AssetStatus::Ready);
+		assert_eq!(status(&states, &items[2]), AssetStatus::Loading);
+		assert_eq!(check_images(&items[..2]), Ok(()));
+	}
+
+	#[test]
+	fn works_out_the_type_if_the_loader_does_not_say() {
+		let server = serve(vec![("/photo", "application/octet-stream", png(1, 1))]);
+		let source = format!("{}/photo", server);
+		let loader = AssetLoader::default();
+		loader.set_with(Some(Rc::new(|source: String| -> LoadFuture {
+			Box::pin(async move { fetch(&source).map(|asset| LoadedAsset { file_type: None, ..asset }) })
+		})));
+
+		let image = AssetStates::default().finish(&source, block_on(loader.load(source.clone()))).unwrap();
+		assert_eq!(image.file_type, "image/png");
+	}
+
+	#[test]
+	fn missing_images_fail_until_retried() {
+		let server = serve(Vec::new());
+		let source = format!("{}/missing.png", server);
+		let items = vec![image_item(1, &source)];
+		let mut states = AssetStates::default();
+
+		assert_eq!(states.start(items.iter()), [source.as_str()]);
+		let err = states.finish(&source, block_on(loader().load(source.clone()))).err().unwrap();
+		assert_eq!(err, "the server responded with 404");
+		assert_eq!(status(&states, &items[0]), AssetStatus::Failed(err));
+		assert_eq!(check_images(&items), Err("photo-1.png hasn't loaded yet".to_string()));
+
+		// Not tried again until the loader changes
+		assert_eq!(states.start(items.iter()), Vec::<String>::new());
+		states.retry();
+		assert_eq!(status(&states, &items[0]), AssetStatus::Loading);
+		assert_eq!(states.start(items.iter()), [source]);
+	}
+
+	#[test]
+	fn rejects_files_that_are_not_images() {
+		let server = serve(vec![("/page.html", "text/html", b"<html></html>".to_vec())]);
+		let source = format!("{}/page.html", server);
+		let items = [image_item(1, &source)];
+		let mut states = AssetStates::default();
+
+		let err = states.finish(&source, block_on(loader().load(source.clone()))).err().unwrap();
+		assert_eq!(err, format!("{} isn't an image", source));
+		assert_eq!(status(&states, &items[0]), AssetStatus::Failed(err));
+	}
+
+	#[test]
+	fn images_without_data_or_a_source_are_broken() {
+		let mut item = image_item(1, "unused");
+		item.file.as_mut().unwrap().source = None;
+
+		assert_eq!(check_images(&[item]), Err("photo-1.png has no image data, so it can't be exported".to_string()));
+	}
+}
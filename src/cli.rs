@@ -0,0 +1,85 @@
+// Command line tools for the native build
+// export-pdf turns a saved design into a PDF without a browser, e.g. on the print shop's server
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::app::pdf::{export_pdf, PdfPage, PdfResources};
+use crate::app::settings::CanvasSettings;
+use crate::page_items::PageItems;
+
+static USAGE: &str = "Usage: web-image-editor export-pdf <design.json> <output.pdf> [--font <family>=<font file>]...
+
+design.json is a list of surfaces, each with a name, canvas_settings and items. Items are in the
+same format the editor saves designs in. Background images are paths relative to the design file";
+
+// A surface in the design file. Each one is a page in the PDF
+#[derive(Deserialize)]
+struct DesignSurface {
+	#[serde(default)]
+	canvas_settings: CanvasSettings,
+	#[serde(default)]
+	items: Vec<PageItems>,
+}
+
+pub fn run() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+
+	let result = match args.first().map(String::as_str) {
+		Some("export-pdf") => export_pdf_command(&args[1..]),
+		_ => Err(USAGE.to_string()),
+	};
+
+	if let Err(err) = result {
+		eprintln!("{}", err);
+		std::process::exit(1);
+	}
+}
+
+fn export_pdf_command(args: &[String]) -> Result<(), String> {
+	let mut paths = Vec::new();
+	let mut fonts = HashMap::new();
+
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		if arg == "--font" {
+			let font = args.next().ok_or(USAGE)?;
+			let (family, path) = font.split_once('=').ok_or(format!("Fonts should be <family>=<font file>, got {}", font))?;
+			fonts.insert(family.to_string(), read(Path::new(path))?);
+		} else {
+			paths.push(PathBuf::from(arg));
+		}
+	}
+
+	let [design_path, output_path] = paths.as_slice() else {
+		return Err(USAGE.to_string());
+	};
+
+	let design = String::from_utf8(read(design_path)?).map_err(|err| format!("{} isn't valid UTF-8: {}", design_path.display(), err))?;
+	let surfaces: Vec<DesignSurface> = serde_json::from_str(&design)
+		.map_err(|err| format!("Failed to parse {}: {}", design_path.display(), err))?;
+
+	// Background images are loaded from files next to the design
+	let mut images = HashMap::new();
+	let design_dir = design_path.parent().unwrap_or(Path::new("."));
+	for surface in &surfaces {
+		if let Some(image) = &surface.canvas_settings.background_image {
+			if !images.contains_key(image) {
+				images.insert(image.clone(), read(&design_dir.join(image))?);
+			}
+		}
+	}
+
+	let pages: Vec<PdfPage> = surfaces.iter()
+		.map(|surface| PdfPage {
+			settings: &surface.canvas_settings,
+			items: &surface.items,
+		})
+		.collect();
+	let pdf = export_pdf(&pages, &PdfResources { fonts, images })?;
+
+	std::fs::write(output_path, pdf).map_err(|err| format!("Failed to write {}: {}", output_path.display(), err))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+	std::fs::read(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))
+}
@@ -0,0 +1,73 @@
+// Copying and pasting items
+// Items are put on the system clipboard as JSON, so they can be pasted into any editor, along with a
+// PNG of images for pasting into other apps
+use std::io::Cursor;
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{console, Element, EventTarget};
+use crate::file_details::FileDetails;
+use crate::page_items::PageItems;
+
+// Javascript functions
+#[wasm_bindgen]
+extern "C" {
+	#[wasm_bindgen(js_name = writeClipboard)]
+	fn write_clipboard(text: &str, png: Option<js_sys::Uint8Array>);
+}
+
+// Copied items. The protocol field tells them apart from other JSON on the clipboard
+#[derive(Serialize, Deserialize)]
+struct ClipboardItems {
+	protocol: String,
+	items: Vec<PageItems>,
+}
+
+static PROTOCOL: &str = "web-image-editor-items";
+
+pub fn copy_items(items: &[PageItems]) {
+	let items: Vec<PageItems> = items.iter().cloned().map(|mut item| {
+		// Images with a source are loaded from it again, the same as saved designs
+		if let Some(file) = item.file.as_mut().filter(|file| file.source.is_some()) {
+			file.data = Vec::new();
+		}
+		item
+	}).collect();
+	let png = match items.as_slice() {
+		[item] => item.file.as_ref().and_then(png_bytes).map(|png| js_sys::Uint8Array::from(png.as_slice())),
+		_ => None,
+	};
+
+	match serde_json::to_string(&ClipboardItems { protocol: PROTOCOL.to_string(), items }) {
+		Ok(json) => write_clipboard(&json, png),
+		Err(err) => console::error_1(&format!("Failed to copy: {}", err).into()),
+	}
+}
+
+// Get the items from pasted text, if it's items copied from an editor
+pub fn parse_items(text: &str) -> Option<Vec<PageItems>> {
+	serde_json::from_str::<ClipboardItems>(text).ok()
+		.filter(|copied| copied.protocol == PROTOCOL)
+		.map(|copied| copied.items)
+}
+
+// Other apps can only paste PNGs from the browser
+fn png_bytes(file: &FileDetails) -> Option<Vec<u8>> {
+	if file.data.is_empty() {
+		return None;
+	}
+	if file.file_type == "image/png" {
+		return Some(file.data.clone());
+	}
+
+	let image = image::load_from_memory(&file.data).ok()?;
+	let mut png = Cursor::new(Vec::new());
+	image.write_to(&mut png, ImageFormat::Png).ok()?;
+	Some(png.into_inner())
+}
+
+// Keys and pastes in text fields are left to them, so text can be edited as usual
+pub fn in_text_field(target: Option<EventTarget>) -> bool {
+	target.and_then(|target| target.dyn_into::<Element>().ok())
+		.is_some_and(|target| matches!(target.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"))
+}
@@ -0,0 +1,209 @@
+// Settings the host page gives an editor when it starts it
+// Anything left out uses the defaults. With query_override on, settings in the query string are
+// applied on top, so links made for the query string still work
+use std::collections::HashMap;
+use serde::Deserialize;
+use super::settings::{CanvasSettings, css_url, SaveSettings, QueryError, DEFAULT_SURFACE, parse_design_query, parse_query, parse_save_query, parse_settings_query, parse_strict_query, parse_surfaces_query};
+use super::surface::SurfaceDesign;
+
+#[derive(Deserialize, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct EditorConfig {
+	// Put in front of the editor's element ids. Every editor on a page needs a different one
+	pub id_prefix: Option<String>,
+	// Origins a parent page can control the editor from when it's in an iframe. The editor only
+	// listens for iframe messages if this is set
+	pub allowed_origins: Option<Vec<String>>,
+	// Canvas settings for every surface that doesn't have its own
+	pub canvas: CanvasSettings,
+	// Printable sides of the product. Without any there's a single surface called "default"
+	pub surfaces: Vec<SurfaceConfig>,
+	// Items to start with, e.g. a design saved with get_design
+	pub design: Vec<SurfaceDesign>,
+	pub tools: Tools,
+	pub save: SaveSettings,
+	// URL of a font file for each font family text can use. They're loaded into the page, and
+	// embedded in PDF exports
+	pub fonts: HashMap<String, String>,
+	// Colours text can be set to. Shown as swatches while a text item is selected
+	pub palette: Vec<String>,
+	// Language the editor's text is in, e.g. "de". The text itself is set with strings
+	pub locale: Option<String>,
+	pub strings: Strings,
+	pub limits: Limits,
+	// Whether settings in the query string override these. Off by default, since every editor on
+	// the page would get the same items from the link. run_app turns it on
+	pub query_override: bool,
+	// Refuse to load if there's anything wrong with the query string, instead of leaving out
+	// whatever's wrong. Can also be turned on with strict_query=true in the query string
+	pub strict_query: bool,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+pub struct SurfaceConfig {
+	pub name: String,
+	// Uses the config's canvas settings if left out
+	#[serde(default)]
+	pub canvas: Option<CanvasSettings>,
+}
+
+// Which of the editor's controls are shown. The host page can do all of these either way
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct Tools {
+	pub upload: bool,
+	pub add_text: bool,
+	pub export_format: bool,
+	pub preview: bool,
+}
+
+// Text shown in the editor
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct Strings {
+	pub add_text: String,
+	// Text of items added with the Add Text button
+	pub new_text: String,
+	pub save: String,
+	pub preview: String,
+	pub edit: String,
+	// {dpi} and {min_dpi} are replaced with the numbers
+	pub low_dpi_badge: String,
+	pub low_dpi_warning: String,
+	pub outside_safe_area_badge: String,
+	pub outside_safe_area_warning: String,
+	// Shown in place of images that are loading from their source, or couldn't be loaded
+	pub image_loading: String,
+	pub image_failed: String,
+	// Shown in template frames the customer hasn't put a photo in yet
+	pub placeholder: String,
+	// Shown above the problems with the query string when strict mode stops the editor loading
+	pub load_failed: String,
+}
+
+#[derive(Deserialize, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Limits {
+	// Most items a surface can have
+	pub max_items: Option<usize>,
+	// Largest image that can be uploaded, in bytes
+	pub max_file_size: Option<u64>,
+}
+
+impl Default for Tools {
+	fn default() -> Self {
+		Self {
+			upload: true,
+			add_text: true,
+			export_format: true,
+			preview: true,
+		}
+	}
+}
+
+impl Default for Strings {
+	fn default() -> Self {
+		Self {
+			add_text: "Add Text".to_string(),
+			new_text: "Hello, World!".to_string(),
+			save: "Save".to_string(),
+			preview: "Preview".to_string(),
+			edit: "Edit".to_string(),
+			low_dpi_badge: "{dpi} DPI".to_string(),
+			low_dpi_warning: "Low print resolution. At least {min_dpi} DPI is recommended".to_string(),
+			outside_safe_area_badge: "Outside safe area".to_string(),
+			outside_safe_area_warning: "This may be cut off when printed".to_string(),
+			image_loading: "Loading image…".to_string(),
+			image_failed: "Image couldn't be loaded".to_string(),
+			placeholder: "Drop your photo here".to_string(),
+			load_failed: "The editor couldn't load because of problems with the link:".to_string(),
+		}
+	}
+}
+
+impl EditorConfig {
+	// The config run_app and the standalone editor use. They're the only editor on the page, so the
+	// link is meant for them
+	pub fn for_page() -> Self {
+		Self {
+			query_override: true,
+			..Self::default()
+		}
+	}
+
+	// Apply the settings and items given in the query string on top of these. Returns any problems
+	// with the query string. In strict mode nothing is applied if there are any
+	pub fn apply_query(&mut self) -> Vec<QueryError> {
+		let mut errors = Vec::new();
+		let original = self.clone();
+
+		let names = parse_surfaces_query();
+		if !names.is_empty() {
+			self.surfaces = names.into_iter()
+				.map(|name| {
+					let canvas = self.surfaces.iter().find(|surface| surface.name == name).and_then(|surface| surface.canvas.clone());
+					SurfaceConfig { name, canvas }
+				})
+				.collect();
+		}
+		if self.surfaces.is_empty() {
+			self.surfaces.push(SurfaceConfig { name: DEFAULT_SURFACE.to_string(), canvas: None });
+		}
+
+		for surface in self.surfaces.iter_mut() {
+			let base = surface.canvas.as_ref().unwrap_or(&self.canvas);
+			surface.canvas = Some(parse_settings_query(Some(&surface.name), base, &mut errors));
+		}
+		self.save = parse_save_query(&self.save, &mut errors);
+
+		// A design in the link replaces the one in the config
+		if let Some(design) = parse_design_query(&mut errors) {
+			self.design = design;
+		}
+
+		// Items without a surface go on the first one
+		for (surface, item) in parse_query(&mut errors) {
+			let name = surface.unwrap_or_else(|| self.surfaces[0].name.clone());
+			match self.design.iter_mut().find(|design| design.name == name) {
+				Some(design) => design.items.push(item),
+				None => self.design.push(SurfaceDesign { name, items: vec![item] }),
+			}
+		}
+
+		let strict_query = parse_strict_query(&mut errors).unwrap_or(self.strict_query);
+		if strict_query && !errors.is_empty() {
+			*self = original;
+		}
+		self.strict_query = strict_query;
+		errors
+	}
+
+	// Check the canvas settings make sense before the editor starts with them
+	pub fn validate(&self) -> Result<(), String> {
+		self.canvas.validate()?;
+		for surface in &self.surfaces {
+			if let Some(canvas) = &surface.canvas {
+				canvas.validate().map_err(|err| format!("{} (surface {})", err, surface.name))?;
+			}
+		}
+		Ok(())
+	}
+
+	// The name and canvas settings of each surface
+	pub fn surface_settings(&self) -> Vec<(String, CanvasSettings)> {
+		if self.surfaces.is_empty() {
+			return vec![(DEFAULT_SURFACE.to_string(), self.canvas.clone())];
+		}
+
+		self.surfaces.iter()
+			.map(|surface| (surface.name.clone(), surface.canvas.clone().unwrap_or_else(|| self.canvas.clone())))
+			.collect()
+	}
+
+	// CSS that loads the fonts into the page
+	pub fn font_faces(&self) -> String {
+		self.fonts.iter()
+			.map(|(family, url)| format!("@font-face {{ font-family: \"{}\"; src: {}; }}\n", family.replace('"', ""), css_url(url)))
+			.collect()
+	}
+}
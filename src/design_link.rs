@@ -0,0 +1,126 @@
+// Designs saved in links, e.g. for "continue editing" links
+// The design is saved as JSON, compressed and encoded as URL safe base64, so the whole design fits
+// in a single design= query parameter
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+use wasm_bindgen::prelude::*;
+use super::surface::SurfaceDesign;
+
+// Javascript functions
+#[wasm_bindgen]
+extern "C" {
+	#[wasm_bindgen(js_name = designUrl)]
+	fn design_url(url: &str, design: &str) -> String;
+}
+
+// Name of the query parameter the design is in
+pub static DESIGN_PARAM: &str = "design";
+
+// Longest encoded design that's put in a link. Many servers refuse URLs much longer than 8KB, and
+// images stored in the design rarely fit, so those need a source instead (see assets.rs)
+pub static MAX_LINK_DESIGN_LENGTH: usize = 8000;
+
+// Largest design that will be decompressed, so a bad link can't use up all the memory
+static MAX_DESIGN_SIZE: usize = 64 * 1024 * 1024;
+
+// Fails if the design is too long for a link
+pub fn encode_design(design: &[SurfaceDesign]) -> Result<String, String> {
+	let json = serde_json::to_vec(design).map_err(|err| err.to_string())?;
+	let encoded = URL_SAFE_NO_PAD.encode(compress_to_vec(&json, 9));
+	if encoded.len() > MAX_LINK_DESIGN_LENGTH {
+		return Err(format!(
+			"The design is too big for a link ({} characters, at most {}). Give images a source so they aren't stored in the design",
+			encoded.len(),
+			MAX_LINK_DESIGN_LENGTH,
+		));
+	}
+	Ok(encoded)
+}
+
+pub fn decode_design(encoded: &str) -> Result<Vec<SurfaceDesign>, String> {
+	// Some tools pad base64 even when it's URL safe
+	let compressed = URL_SAFE_NO_PAD.decode(encoded.trim_end_matches('=')).map_err(|err| format!("The design isn't valid base64: {}", err))?;
+	let json = decompress_to_vec_with_limit(&compressed, MAX_DESIGN_SIZE).map_err(|err| format!("Failed to decompress the design: {}", err))?;
+	serde_json::from_slice(&json).map_err(|err| format!("Invalid design: {}", err))
+}
+
+// Link to url with the design in it. Items given the old way are taken out of the link, so they
+// aren't added twice
+pub fn design_link(url: &str, design: &[SurfaceDesign]) -> Result<String, String> {
+	Ok(design_url(url, &encode_design(design)?))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::file_details::FileDetails;
+	use crate::page_items::PageItemsBuilder;
+	use crate::text_details::TextDetailsBuilder;
+	use super::*;
+
+	fn image_design(data: Vec<u8>, source: Option<String>) -> Vec<SurfaceDesign> {
+		let file = FileDetails {
+			name: "photo.png".to_string(),
+			file_type: "image/png".to_string(),
+			data,
+			width: 40,
+			height: 20,
+			source,
+		};
+		let text = TextDetailsBuilder::default().text("Hello".to_string()).build().unwrap();
+		let items = vec![
+			PageItemsBuilder::default().id(1).file(Some(file)).x(5.0).y(10.0).width(Some(80.0)).build().unwrap(),
+			PageItemsBuilder::default().id(2).text(Some(text)).x(20.0).y(30.0).build().unwrap(),
+		];
+		vec![SurfaceDesign { name: "front".to_string(), items }]
+	}
+
+	// Bytes that don't compress, like a real photo
+	fn noise(length: usize) -> Vec<u8> {
+		let mut state = 1u32;
+		(0..length).map(|_| {
+			state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+			(state >> 24) as u8
+		}).collect()
+	}
+
+	#[test]
+	fn round_trip() {
+		let design = image_design(vec![1, 2, 3], None);
+		let encoded = encode_design(&design).unwrap();
+
+		assert!(encoded.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'), "{} isn't URL safe", encoded);
+		assert!(decode_design(&encoded).unwrap() == design);
+	}
+
+	#[test]
+	fn images_with_a_source_round_trip() {
+		let design = image_design(Vec::new(), Some("asset-123".to_string()));
+		assert!(decode_design(&encode_design(&design).unwrap()).unwrap() == design);
+	}
+
+	#[test]
+	fn padded_input() {
+		let design = image_design(vec![1, 2, 3], None);
+		let mut encoded = encode_design(&design).unwrap();
+		while !encoded.len().is_multiple_of(4) {
+			encoded.push('=');
+		}
+
+		assert!(decode_design(&encoded).unwrap() == design);
+	}
+
+	#[test]
+	fn over_the_limit() {
+		let err = encode_design(&image_design(noise(MAX_LINK_DESIGN_LENGTH), None)).err().unwrap();
+		assert!(err.starts_with("The design is too big for a link"), "{}", err);
+	}
+
+	#[test]
+	fn invalid_input() {
+		assert!(decode_design("not base64!").err().unwrap().starts_with("The design isn't valid base64"));
+		assert!(decode_design(&URL_SAFE_NO_PAD.encode(b"not compressed")).err().unwrap().starts_with("Failed to decompress"));
+		assert!(decode_design(&URL_SAFE_NO_PAD.encode(compress_to_vec(b"{}", 9))).err().unwrap().starts_with("Invalid design"));
+	}
+}
@@ -1,7 +1,17 @@
 // Contains the base editable canvas div component
 // This basically does nothing but contain other divs and not allow its children to overflow or change the size of the canvas div
 // Note that this is just a div, not an actual HTML <cavas>
+// Bleed, trim line and safe zone overlays are drawn over the children when they're set
+// If there's a mask the children are clipped to it, and the area outside of it is dimmed
+// The background is drawn behind the children in its own div, so exports can leave it out
+// Image files can be dropped on the canvas. Anything pasted while it or something on it has focus is
+// given to the app, unless it was pasted into a text field
+use gloo::events::EventListener;
+use wasm_bindgen::JsCast;
+use web_sys::{ClipboardEvent, DataTransfer, Element, FileList};
 use yew::prelude::*;
+use super::clipboard::in_text_field;
+use super::settings::{DEFAULT_CANVAS_SIZE, css_url};
 
 #[derive(PartialEq, Properties)]
 pub struct CanvasProps {
@@ -13,10 +23,33 @@ pub struct CanvasProps {
 	pub style: AttrValue,
 	#[prop_or_default]
 	pub children: Children,
+	// Size on screen, in pixels
 	#[prop_or(None)]
 	pub width: Option<i32>,
 	#[prop_or(None)]
 	pub height: Option<i32>,
+	// Size of the bleed and safe margin, in pixels. Shown as overlays that aren't exported
+	#[prop_or(0)]
+	pub bleed: i32,
+	#[prop_or(0)]
+	pub safe_margin: i32,
+	// URL of the image to use as the printable area mask
+	#[prop_or(None)]
+	pub mask: Option<AttrValue>,
+	#[prop_or(None)]
+	pub background_color: Option<AttrValue>,
+	// URL of the background image
+	#[prop_or(None)]
+	pub background_image: Option<AttrValue>,
+	// Called with files dropped on the canvas, and where they were dropped in pixels from its top left
+	#[prop_or_default]
+	pub ondropfiles: Callback<(FileList, i32, i32)>,
+	// Called with what was pasted
+	#[prop_or_default]
+	pub onpaste: Callback<DataTransfer>,
+	// Keys pressed on the canvas or anything on it
+	#[prop_or_default]
+	pub onkeydown: Callback<KeyboardEvent>,
 }
 
 #[function_component]
@@ -26,12 +59,79 @@ pub fn EditableCanvas(props: &CanvasProps) -> Html {
 	let id = props.id.clone();
 	let extra_style = props.style.clone();
 
-	let width = props.width.unwrap_or(800);
-	let height = props.height.unwrap_or(800);
+	// Yew doesn't give paste events as clipboard events, so this listens for them itself
+	{
+		let canvas_node_ref = canvas_node_ref.clone();
+		use_effect_with(props.onpaste.clone(), move |onpaste| {
+			let onpaste = onpaste.clone();
+			let listener = canvas_node_ref.cast::<Element>().map(|canvas| {
+				EventListener::new(&canvas, "paste", move |event| {
+					let Some(event) = event.dyn_ref::<ClipboardEvent>() else {
+						return;
+					};
+					if in_text_field(event.target()) {
+						return;
+					}
+					if let Some(data) = event.clipboard_data() {
+						event.prevent_default();
+						onpaste.emit(data);
+					}
+				})
+			});
+			move || drop(listener)
+		});
+	}
+
+	// Files can only be dropped where the drag over is cancelled
+	let ondragover = |event: DragEvent| event.prevent_default();
+
+	let ondrop = {
+		let canvas_node_ref = canvas_node_ref.clone();
+		let ondropfiles = props.ondropfiles.clone();
+		move |event: DragEvent| {
+			// Otherwise the browser opens the file
+			event.prevent_default();
+			let (Some(files), Some(canvas)) = (event.data_transfer().and_then(|data| data.files()), canvas_node_ref.cast::<Element>()) else {
+				return;
+			};
+			let bounds = canvas.get_bounding_client_rect();
+			ondropfiles.emit((files, event.client_x() - bounds.left() as i32, event.client_y() - bounds.top() as i32));
+		}
+	};
+
+	let width = props.width.unwrap_or(DEFAULT_CANVAS_SIZE);
+	let height = props.height.unwrap_or(DEFAULT_CANVAS_SIZE);
+
+	let mut background_style = "position: absolute; inset: 0; z-index: 0; pointer-events: none;".to_string();
+	if let Some(color) = &props.background_color {
+		background_style.push_str(&format!(" background-color: {};", color));
+	}
+	if let Some(image) = &props.background_image {
+		background_style.push_str(&format!(" background-image: {}; background-size: cover; background-position: center; background-repeat: no-repeat;", image));
+	}
+
+	let mask = props.mask.as_deref().map(css_url);
 
 	html! {
-		<div ref={canvas_node_ref} {id} style={format!("position: absolute; width: {}px; height: {}px; max-width: {}px; max-height: {}px; overflow: hidden; {}", width, height, width, height, extra_style)} >
-			{ props.children.clone() }
+		<div ref={canvas_node_ref} {id} class={props.class.clone()} tabindex="0" {ondragover} {ondrop} onkeydown={props.onkeydown.clone()} style={format!("position: absolute; width: {}px; height: {}px; max-width: {}px; max-height: {}px; overflow: hidden; {}", width, height, width, height, extra_style)} >
+			if props.background_color.is_some() || props.background_image.is_some() {
+				<div class="canvas-background" style={background_style} />
+			}
+			if let Some(mask) = mask {
+				<div class="printable-area" style={format!("position: absolute; inset: 0; mask-image: {0}; -webkit-mask-image: {0}; mask-size: 100% 100%; -webkit-mask-size: 100% 100%;", mask)}>
+					{ props.children.clone() }
+				</div>
+				<div class="mask-outside" data-html2canvas-ignore="true" style={format!("position: absolute; inset: 0; pointer-events: none; z-index: 1999; mask-image: linear-gradient(#000 0 0), {0}; -webkit-mask-image: linear-gradient(#000 0 0), {0}; mask-size: 100% 100%; -webkit-mask-size: 100% 100%; mask-composite: exclude; -webkit-mask-composite: xor;", mask)} />
+			} else {
+				{ props.children.clone() }
+			}
+			if props.bleed > 0 {
+				<div class="bleed-overlay" data-html2canvas-ignore="true" style={format!("position: absolute; inset: 0; border-width: {}px; border-style: solid; box-sizing: border-box; pointer-events: none; z-index: 2000;", props.bleed)} />
+				<div class="trim-line" data-html2canvas-ignore="true" style={format!("position: absolute; inset: {}px; pointer-events: none; z-index: 2000;", props.bleed)} />
+			}
+			if props.safe_margin > 0 {
+				<div class="safe-zone" data-html2canvas-ignore="true" style={format!("position: absolute; inset: {}px; pointer-events: none; z-index: 2000;", props.bleed + props.safe_margin)} />
+			}
 		</div>
 	}
 }
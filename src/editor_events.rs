@@ -0,0 +1,232 @@
+// Events the host page can listen for, either with EditorHandle::on or as
+// "web-image-editor:<name>" events on the editor's element, which bubble up to the document
+// change: an item was added, updated or removed, a surface's background changed, or the design was replaced
+// selection: the user selected an item, or the selected item went away
+// validation: the editor started, or the design's print warnings changed (see EditorHandle::validate)
+// export, export-error: an export finished or failed
+// limit: an item or upload was turned away because of the config's limits
+// query-errors: there were problems with the query string when the editor started
+// asset-error: an image couldn't be loaded from its source
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::{console, Element};
+use crate::page_items::{ItemRole, PageItems};
+use super::print_quality::{DpiWarning, SafeZoneWarning};
+use super::settings::QueryError;
+use super::surface::Surface;
+
+// Javascript functions
+#[wasm_bindgen]
+extern "C" {
+	#[wasm_bindgen(js_name = dispatchEditorEvent)]
+	fn dispatch_editor_event(target: &JsValue, name: &str, detail: JsValue);
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+	Add,
+	Update,
+	Remove,
+	Background,
+	// Any other canvas settings, e.g. the size or mask
+	Settings,
+	// The whole design was replaced
+	Design,
+}
+
+#[derive(Serialize)]
+pub struct ChangeEvent<'a> {
+	#[serde(rename = "type")]
+	pub kind: ChangeKind,
+	pub surface: &'a str,
+	// Not set for background, settings and design changes
+	pub item_id: Option<u32>,
+	// Not set for background, settings and design changes, and removed items
+	pub item: Option<ItemSummary<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct SelectionEvent<'a> {
+	pub surface: Option<&'a str>,
+	// None if nothing is selected
+	pub item_id: Option<u32>,
+	pub item: Option<ItemSummary<'a>>,
+}
+
+// What events say about an item. Events are sent on every drag and key press, so they leave out
+// the image data. EditorHandle::get_item has the whole item
+#[derive(Serialize)]
+pub struct ItemSummary<'a> {
+	pub id: u32,
+	pub role: ItemRole,
+	pub x: f64,
+	pub y: f64,
+	pub width: Option<f64>,
+	pub height: Option<f64>,
+	pub text: Option<&'a str>,
+	pub file: Option<FileSummary<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct FileSummary<'a> {
+	pub name: &'a str,
+	// Size of the image in pixels. 0 until an image with a source has loaded
+	pub width: i32,
+	pub height: i32,
+	pub source: Option<&'a str>,
+}
+
+impl<'a> From<&'a PageItems> for ItemSummary<'a> {
+	fn from(item: &'a PageItems) -> Self {
+		Self {
+			id: item.id,
+			role: item.role,
+			x: item.x,
+			y: item.y,
+			width: item.width,
+			height: item.height,
+			text: item.text.as_ref().map(|text| text.text.as_str()),
+			file: item.file.as_ref().map(|file| FileSummary {
+				name: &file.name,
+				width: file.width,
+				height: file.height,
+				source: file.source.as_deref(),
+			}),
+		}
+	}
+}
+
+#[derive(Serialize)]
+pub struct LimitEvent<'a> {
+	// Name of the limit in the config, e.g. max_items
+	pub limit: &'a str,
+	pub surface: Option<&'a str>,
+	pub file: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+pub struct QueryErrorsEvent<'a> {
+	// In strict mode the editor doesn't load anything from the query string if there are errors
+	pub refused: bool,
+	pub errors: &'a [QueryError],
+}
+
+#[derive(Serialize)]
+pub struct AssetErrorEvent<'a> {
+	pub source: &'a str,
+	pub error: &'a str,
+}
+
+// Every problem found with the design. The design is good to go if valid is true
+#[derive(Serialize)]
+pub struct Validation<'a> {
+	pub valid: bool,
+	pub print_warnings: Vec<&'a DpiWarning>,
+	pub safe_zone_warnings: Vec<&'a SafeZoneWarning>,
+	// Ids of template placeholders that still need a photo
+	pub empty_placeholders: Vec<u32>,
+}
+
+impl<'a> Validation<'a> {
+	pub fn new(surfaces: &'a [Surface]) -> Self {
+		let print_warnings: Vec<&DpiWarning> = surfaces.iter().flat_map(|surface| surface.print_warnings.iter()).collect();
+		let safe_zone_warnings: Vec<&SafeZoneWarning> = surfaces.iter().flat_map(|surface| surface.safe_zone_warnings.iter()).collect();
+		let empty_placeholders: Vec<u32> = surfaces.iter().flat_map(|surface| surface.empty_placeholders.iter().copied()).collect();
+		Self {
+			valid: print_warnings.is_empty() && safe_zone_warnings.is_empty() && empty_placeholders.is_empty(),
+			print_warnings,
+			safe_zone_warnings,
+			empty_placeholders,
+		}
+	}
+}
+
+// Listeners added by the host page, shared between the app and its EditorHandle
+#[derive(Clone, Default)]
+pub struct EditorEvents {
+	listeners: Rc<RefCell<HashMap<String, Vec<js_sys::Function>>>>,
+	// Element events are dispatched on. The document if there isn't one
+	target: Option<Element>,
+}
+
+impl PartialEq for EditorEvents {
+	fn eq(&self, other: &Self) -> bool {
+		Rc::ptr_eq(&self.listeners, &other.listeners)
+	}
+}
+
+impl EditorEvents {
+	pub fn new(target: Option<Element>) -> Self {
+		Self {
+			listeners: Rc::default(),
+			target,
+		}
+	}
+
+	pub fn on(&self, name: String, callback: js_sys::Function) {
+		self.listeners.borrow_mut().entry(name).or_default().push(callback);
+	}
+
+	pub fn off(&self, name: &str, callback: &js_sys::Function) {
+		if let Some(listeners) = self.listeners.borrow_mut().get_mut(name) {
+			listeners.retain(|listener| listener != callback);
+		}
+	}
+
+	// Send an event to every listener, then dispatch it on the editor's element
+	pub fn emit<T: Serialize>(&self, name: &str, detail: &T) {
+		match serde_wasm_bindgen::to_value(detail) {
+			Ok(detail) => self.emit_value(name, detail),
+			Err(err) => console::error_1(&format!("Failed to serialize {} event: {}", name, err).into()),
+		}
+	}
+
+	pub fn emit_value(&self, name: &str, detail: JsValue) {
+		// Listeners might add or remove listeners, so don't hold on to the list while calling them
+		let listeners = self.listeners.borrow().get(name).cloned().unwrap_or_default();
+		for listener in listeners {
+			if let Err(err) = listener.call1(&JsValue::NULL, &detail) {
+				console::error_2(&format!("Listener for {} event failed:", name).into(), &err);
+			}
+		}
+
+		let target = self.target.as_ref().map(JsValue::from).unwrap_or(JsValue::UNDEFINED);
+		dispatch_editor_event(&target, name, detail);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::file_details::FileDetails;
+	use crate::page_items::PageItemsBuilder;
+	use super::*;
+
+	#[test]
+	fn summaries_leave_out_image_data() {
+		let file = FileDetails {
+			name: "photo.png".to_string(),
+			file_type: "image/png".to_string(),
+			data: vec![1, 2, 3],
+			width: 40,
+			height: 20,
+			source: Some("asset-1".to_string()),
+		};
+		let item = PageItemsBuilder::default().id(7).file(Some(file)).x(5.0).width(Some(80.0)).build().unwrap();
+
+		let summary = serde_json::to_value(ItemSummary::from(&item)).unwrap();
+		assert_eq!(summary, serde_json::json!({
+			"id": 7,
+			"role": "free",
+			"x": 5.0,
+			"y": 0.0,
+			"width": 80.0,
+			"height": null,
+			"text": null,
+			"file": { "name": "photo.png", "width": 40, "height": 20, "source": "asset-1" },
+		}));
+	}
+}
@@ -0,0 +1,338 @@
+// Lets the host page control the editor from Javascript
+// Changes are sent to the app as messages, so they show up in queries once the app has updated,
+// which happens as soon as the current Javascript task is done
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use yew::html::Scope;
+use crate::page_items::{ItemRole, PageItems, next_item_id};
+use crate::mockup::MockupTemplate;
+use super::{App, CanvasBackground, Msg, export};
+use super::export::ExportOptions;
+use super::editor_events::{EditorEvents, Validation};
+use super::assets::AssetLoader;
+use super::design_link::design_link;
+use super::settings::{CanvasSettings, QueryError};
+use super::surface::{Surface, SurfaceDesign};
+
+// Name of the global the js/ helper functions use to find the editor
+static DEFAULT_EDITOR_GLOBAL: &str = "webImageEditor";
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct EditorHandle {
+	scope: Scope<App>,
+	// The same listeners the app was given, so listeners can be added before it's created
+	events: EditorEvents,
+	assets: AssetLoader,
+}
+
+impl EditorHandle {
+	pub fn new(scope: Scope<App>, events: EditorEvents, assets: AssetLoader) -> Self {
+		Self { scope, events, assets }
+	}
+
+	// Whether an editor has been made the one the js/ helper functions use
+	pub fn has_default() -> bool {
+		let window = web_sys::window().unwrap();
+		js_sys::Reflect::get(&window, &DEFAULT_EDITOR_GLOBAL.into()).is_ok_and(|editor| !editor.is_undefined())
+	}
+
+	// Make this the editor the js/ helper functions use
+	pub fn set_default(&self) {
+		let window = web_sys::window().unwrap();
+		if js_sys::Reflect::set(&window, &DEFAULT_EDITOR_GLOBAL.into(), &self.clone().into()).is_err() {
+			web_sys::console::error_1(&"Failed to set the default editor".into());
+		}
+	}
+
+	pub(crate) fn events(&self) -> &EditorEvents {
+		&self.events
+	}
+
+	// Run f with the app, which isn't there until it's been created
+	fn with_app<T>(&self, f: impl FnOnce(&App) -> Result<T, String>) -> Result<T, JsValue> {
+		let app = self.scope.get_component().ok_or("The editor hasn't started yet")?;
+		f(&app).map_err(JsValue::from)
+	}
+
+	// The rest of these are the Javascript functions below after their arguments have been parsed.
+	// Iframe messages are parsed into the same types, so they use these directly
+
+	pub(crate) fn add_page_item(&self, mut item: PageItems, surface: Option<String>) -> Result<u32, JsValue> {
+		if item.text.is_some() && item.file.is_some() || item.text.is_none() && item.file.is_none() && item.role != ItemRole::Placeholder {
+			return Err("Items need either text or a file. Only placeholders can be empty".into());
+		}
+
+		// Items can come from saved designs, so give it an id that's not in use
+		item.id = next_item_id();
+		let id = item.id;
+
+		// Surfaces are set up when the app is created, so they can't be checked before then
+		if let Some(app) = self.scope.get_component() {
+			let found = find_surface(&app, &surface)?;
+			if app.config.limits.max_items.is_some_and(|max_items| found.items.len() >= max_items) {
+				return Err(format!("{} already has the most items allowed", found.name).into());
+			}
+		}
+
+		match surface {
+			Some(name) => self.scope.send_message(Msg::SurfaceItem(name, item)),
+			None => self.scope.send_message(Msg::Item(item)),
+		}
+		Ok(id)
+	}
+
+	// Changes have to be ones the item's role allows, unless force is set, e.g. by the template's
+	// author
+	pub(crate) fn merge_item(&self, id: u32, changes: serde_json::Value, force: bool) -> Result<(), JsValue> {
+		let item = self.with_app(|app| {
+			let original = find_item(app, id)?;
+			let mut item = serde_json::to_value(original).map_err(|err| err.to_string())?;
+			merge(&mut item, changes);
+
+			let mut item: PageItems = serde_json::from_value(item).map_err(|err| format!("Invalid changes: {}", err))?;
+			item.id = id;
+			if !force {
+				original.check_changes(&item)?;
+			}
+			Ok(item)
+		})?;
+
+		self.scope.send_message(Msg::UpdateItem(item));
+		Ok(())
+	}
+
+	pub(crate) fn replace_design(&self, design: Vec<SurfaceDesign>) -> Result<(), JsValue> {
+		self.with_app(|app| {
+			for surface in &design {
+				if !app.surfaces.iter().any(|found| found.name == surface.name) {
+					return Err(format!("No surface called {}", surface.name));
+				}
+			}
+			Ok(())
+		})?;
+		self.scope.send_message(Msg::SetDesign(design));
+		Ok(())
+	}
+
+	pub(crate) fn set_mockup_templates(&self, mockups: Vec<MockupTemplate>) {
+		self.scope.send_message(Msg::SetMockups(mockups));
+	}
+
+	pub(crate) fn set_background(&self, background: CanvasBackground, surface: Option<String>) -> Result<(), JsValue> {
+		self.with_app(|app| find_surface(app, &surface).map(|_| ()))?;
+		self.scope.send_message(Msg::SetBackground(surface, background));
+		Ok(())
+	}
+
+	// Settings are validated the same as the config's, so a canvas can't be left without a size
+	pub(crate) fn merge_settings(&self, changes: serde_json::Value, surface: Option<String>) -> Result<(), JsValue> {
+		let (name, settings) = self.with_app(|app| {
+			let surface = find_surface(app, &surface)?;
+			let mut settings = serde_json::to_value(&surface.canvas_settings).map_err(|err| err.to_string())?;
+			merge(&mut settings, changes);
+
+			let settings: CanvasSettings = serde_json::from_value(settings).map_err(|err| format!("Invalid settings: {}", err))?;
+			settings.validate()?;
+			Ok((surface.name.clone(), settings))
+		})?;
+		self.scope.send_message(Msg::SetSettings(name, settings));
+		Ok(())
+	}
+
+	pub(crate) fn export_with(&self, options: ExportOptions, surface: Option<String>) -> Result<js_sys::Promise, JsValue> {
+		let (surface, options) = self.with_app(|app| Ok((find_surface(app, &surface)?.clone(), app.with_fonts(options))))?;
+		let events = self.events.clone();
+
+		Ok(wasm_bindgen_futures::future_to_promise(async move {
+			export(surface, options, events).await.map_err(JsValue::from)
+		}))
+	}
+}
+
+// Everything the host page needs to know to show the design
+#[derive(Serialize)]
+struct EditorState<'a> {
+	active_surface: &'a str,
+	selected_item: Option<u32>,
+	surfaces: Vec<SurfaceDesign>,
+	validation: Validation<'a>,
+	query_errors: &'a [QueryError],
+}
+
+// Find a surface by name, or the one being edited
+fn find_surface<'a>(app: &'a App, name: &Option<String>) -> Result<&'a Surface, String> {
+	match name {
+		Some(name) => app.surfaces.iter().find(|surface| &surface.name == name).ok_or(format!("No surface called {}", name)),
+		None => Ok(app.surface()),
+	}
+}
+
+fn find_item(app: &App, id: u32) -> Result<&PageItems, String> {
+	app.surfaces.iter()
+		.flat_map(|surface| surface.items.iter())
+		.find(|item| item.id == id)
+		.ok_or(format!("No item with id {}", id))
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, String> {
+	serde_wasm_bindgen::to_value(value).map_err(|err| err.to_string())
+}
+
+// Overwrite the fields in target with the ones in changes. Objects are merged rather than replaced,
+// so e.g. only the font size of an item's text can be changed
+fn merge(target: &mut serde_json::Value, changes: serde_json::Value) {
+	match (target, changes) {
+		(serde_json::Value::Object(target), serde_json::Value::Object(changes)) => {
+			for (key, value) in changes {
+				match target.get_mut(&key) {
+					Some(existing) if existing.is_object() => merge(existing, value),
+					_ => {
+						target.insert(key, value);
+					}
+				}
+			}
+		}
+		(target, changes) => *target = changes,
+	}
+}
+
+#[wasm_bindgen]
+impl EditorHandle {
+	// Add an item to a surface, or the one being edited. Returns the new item's id
+	pub fn add_item(&self, item: JsValue, surface: Option<String>) -> Result<u32, JsValue> {
+		let item: PageItems = serde_wasm_bindgen::from_value(item).map_err(|err| format!("Invalid item: {}", err))?;
+		self.add_page_item(item, surface)
+	}
+
+	// Change some of an item's fields, e.g. {x: 10, text: {font_size: 20}}
+	// Items in a template can only be changed the way their role allows, unless force is true
+	pub fn update_item(&self, id: u32, changes: JsValue, force: Option<bool>) -> Result<(), JsValue> {
+		let changes: serde_json::Value = serde_wasm_bindgen::from_value(changes).map_err(|err| format!("Invalid changes: {}", err))?;
+		self.merge_item(id, changes, force.unwrap_or(false))
+	}
+
+	// Only free items can be removed, unless force is true
+	pub fn remove_item(&self, id: u32, force: Option<bool>) -> Result<(), JsValue> {
+		self.with_app(|app| {
+			let item = find_item(app, id)?;
+			match force.unwrap_or(false) {
+				true => Ok(()),
+				false => item.check_remove(),
+			}
+		})?;
+		self.scope.send_message(Msg::Remove(id));
+		Ok(())
+	}
+
+	pub fn get_item(&self, id: u32) -> Result<JsValue, JsValue> {
+		self.with_app(|app| to_js(find_item(app, id)?))
+	}
+
+	// Get every item on a surface, or the one being edited. The first item is on top
+	pub fn get_items(&self, surface: Option<String>) -> Result<JsValue, JsValue> {
+		self.with_app(|app| to_js(&find_surface(app, &surface)?.items))
+	}
+
+	// Get the saved form of the design, as a list of {name, items} for each surface
+	// surface is the name of a single surface to get. Leave it out to get every surface
+	pub fn get_design(&self, surface: Option<String>) -> Result<JsValue, JsValue> {
+		self.with_app(|app| {
+			let designs: Vec<SurfaceDesign> = match surface {
+				Some(_) => vec![find_surface(app, &surface)?.design()],
+				None => app.surfaces.iter().map(Surface::design).collect(),
+			};
+			to_js(&designs)
+		})
+	}
+
+	// Replace the whole design, e.g. with a template. Takes a list of {name, items} for each surface,
+	// the same as get_design. Surfaces that aren't in it are cleared
+	pub fn set_design(&self, design: JsValue) -> Result<(), JsValue> {
+		let design: Vec<SurfaceDesign> = serde_wasm_bindgen::from_value(design).map_err(|err| format!("Invalid design: {}", err))?;
+		self.replace_design(design)
+	}
+
+	// Set the product mockups the design can be previewed on
+	pub fn set_mockups(&self, mockups: JsValue) -> Result<(), JsValue> {
+		let mockups: Vec<MockupTemplate> = serde_wasm_bindgen::from_value(mockups).map_err(|err| format!("Invalid mockups: {}", err))?;
+		self.set_mockup_templates(mockups);
+		Ok(())
+	}
+
+	// Set the background of a surface, or the one being edited. Takes {color, image, transparent_export}
+	pub fn set_canvas_background(&self, background: JsValue, surface: Option<String>) -> Result<(), JsValue> {
+		let background: CanvasBackground = serde_wasm_bindgen::from_value(background).map_err(|err| format!("Invalid background: {}", err))?;
+		self.set_background(background, surface)
+	}
+
+	// Change some of the canvas settings of a surface, or the one being edited, e.g. {width: 100, unit: "mm"}
+	// Takes the same fields as the config's canvas. Items keep their geometry, so changing the unit
+	// changes their size too
+	pub fn update_settings(&self, settings: JsValue, surface: Option<String>) -> Result<(), JsValue> {
+		let settings: serde_json::Value = serde_wasm_bindgen::from_value(settings).map_err(|err| format!("Invalid settings: {}", err))?;
+		self.merge_settings(settings, surface)
+	}
+
+	// Export a surface, or the one being edited. Resolves with the export, the same as the
+	// web-image-editor:export event
+	pub fn export(&self, options: JsValue, surface: Option<String>) -> Result<js_sys::Promise, JsValue> {
+		let options: ExportOptions = if options.is_undefined() || options.is_null() {
+			ExportOptions::default()
+		} else {
+			serde_wasm_bindgen::from_value(options).map_err(|err| format!("Invalid export options: {}", err))?
+		};
+		self.export_with(options, surface)
+	}
+
+	// Get a link that opens the editor with the design as it is now, for continuing it later
+	// url is the page to link to, and defaults to the current one. The page needs query_override
+	// on for the link to work, which run_app has. Fails if the design is too big for a link
+	pub fn get_design_link(&self, url: Option<String>) -> Result<String, JsValue> {
+		let url = match url {
+			Some(url) => url,
+			None => web_sys::window().unwrap().location().href()?,
+		};
+		self.with_app(|app| {
+			let design: Vec<SurfaceDesign> = app.surfaces.iter().map(Surface::design).collect();
+			design_link(&url, &design)
+		})
+	}
+
+	// Get the whole design along with what's being edited and whether it's ready to order
+	pub fn get_state(&self) -> Result<JsValue, JsValue> {
+		self.with_app(|app| {
+			to_js(&EditorState {
+				active_surface: &app.surface().name,
+				selected_item: app.selected,
+				surfaces: app.surfaces.iter().map(Surface::design).collect(),
+				validation: Validation::new(&app.surfaces),
+				query_errors: &app.query_errors,
+			})
+		})
+	}
+
+	// Check the design before it's ordered
+	pub fn validate(&self) -> Result<JsValue, JsValue> {
+		self.with_app(|app| to_js(&Validation::new(&app.surfaces)))
+	}
+
+	// Call callback with the event's details whenever it happens. Events are change, selection,
+	// validation, export, export-error, print-warnings and safe-zone-warnings
+	pub fn on(&self, name: String, callback: js_sys::Function) {
+		self.events.on(name, callback);
+	}
+
+	// Stop calling a callback added with on
+	pub fn off(&self, name: String, callback: js_sys::Function) {
+		self.events.off(&name, &callback);
+	}
+
+	// Set how images with a source are loaded. The loader is called with the source, and returns
+	// or resolves to the image as a Blob, Response, ArrayBuffer or Uint8Array, or a URL to fetch
+	// it from. Without one, sources are fetched as URLs. Images that failed to load are tried again
+	pub fn set_asset_loader(&self, loader: Option<js_sys::Function>) {
+		self.assets.set(loader);
+		self.scope.send_message(Msg::ReloadAssets);
+	}
+}
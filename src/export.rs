@@ -0,0 +1,436 @@
+// Renders a surface to an image file
+// Images are decoded, scaled and composited in Rust, the background, mask and text are drawn in
+// Rust (see raster.rs), and the file is encoded in Rust as well, so exports come out the same in
+// every browser. The browser only draws what Rust can't: text in a font family with no font file,
+// and background or mask images in formats the image crate can't read, like SVG
+// PDFs and SVGs aren't rendered at all. See pdf.rs and svg.rs
+use std::collections::HashMap;
+use std::io::Cursor;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ExtendedColorType, ImageFormat, Rgba, RgbaImage};
+use ttf_parser::Face;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement, Response};
+use crate::file_details::FileDetails;
+use crate::page_items::{ImageCrop, PageItems};
+use crate::text_details::TextDetails;
+use super::pdf::{export_pdf, PdfPage, PdfResources};
+use super::print_quality::item_area;
+use super::raster::{self, parse_color, wrap_text, Outline, LINE_HEIGHT};
+use super::svg::export_svg;
+use super::settings::CanvasSettings;
+use super::surface::Surface;
+
+#[derive(PartialEq, Clone, Copy, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+	Png,
+	Jpeg,
+	Webp,
+	Pdf,
+	Svg,
+}
+
+impl ExportFormat {
+	pub fn mime_type(&self) -> &'static str {
+		match self {
+			ExportFormat::Png => "image/png",
+			ExportFormat::Jpeg => "image/jpeg",
+			ExportFormat::Webp => "image/webp",
+			ExportFormat::Pdf => "application/pdf",
+			ExportFormat::Svg => "image/svg+xml",
+		}
+	}
+
+	pub fn extension(&self) -> &'static str {
+		match self {
+			ExportFormat::Png => "png",
+			ExportFormat::Jpeg => "jpg",
+			ExportFormat::Webp => "webp",
+			ExportFormat::Pdf => "pdf",
+			ExportFormat::Svg => "svg",
+		}
+	}
+
+	pub fn parse(format: &str) -> Option<ExportFormat> {
+		match format.to_lowercase().as_str() {
+			"png" => Some(ExportFormat::Png),
+			"jpeg" | "jpg" => Some(ExportFormat::Jpeg),
+			"webp" => Some(ExportFormat::Webp),
+			"pdf" => Some(ExportFormat::Pdf),
+			"svg" => Some(ExportFormat::Svg),
+			_ => None,
+		}
+	}
+}
+
+#[derive(PartialEq, Clone, Deserialize, Debug)]
+#[serde(default)]
+pub struct ExportOptions {
+	pub format: ExportFormat,
+	// Quality from 1 to 100, defaulting to 90. Only JPEG exports use it. WebP exports are always
+	// lossless and PNG, PDF and SVG exports have no quality, so it's ignored for those
+	pub quality: Option<u8>,
+	// Extra scale on top of what's needed to reach the canvas' target DPI. Not used for PDFs or SVGs
+	pub scale: f64,
+	// Defaults to the name of the surface
+	pub filename: Option<String>,
+	// URLs of font files, keyed by font family. PDFs embed them, and image exports draw text with them
+	pub fonts: HashMap<String, String>,
+	// Whether to save the file in the browser. The bytes are sent to the host page either way
+	pub download: bool,
+}
+
+impl Default for ExportOptions {
+	fn default() -> Self {
+		Self {
+			format: ExportFormat::Png,
+			quality: None,
+			scale: 1.0,
+			filename: None,
+			fonts: HashMap::new(),
+			download: true,
+		}
+	}
+}
+
+impl ExportOptions {
+	pub fn filename_for(&self, surface: &Surface) -> String {
+		self.filename.clone().unwrap_or_else(|| format!("{}.{}", surface.name, self.format.extension()))
+	}
+}
+
+// Render a surface and encode it in the requested format
+pub async fn export_surface(surface: &Surface, options: &ExportOptions) -> Result<Vec<u8>, String> {
+	check_images(&surface.items)?;
+
+	if options.format == ExportFormat::Pdf {
+		let resources = load_pdf_resources(surface, options).await?;
+		let page = PdfPage {
+			settings: &surface.canvas_settings,
+			items: &surface.items,
+		};
+		return export_pdf(&[page], &resources);
+	}
+
+	if options.format == ExportFormat::Svg {
+		let images = load_svg_images(&surface.canvas_settings).await?;
+		return Ok(export_svg(&surface.canvas_settings, &surface.items, &images).into_bytes());
+	}
+
+	let fonts = load_fonts(surface, options).await?;
+	let image = render_surface(surface, options.scale, &fonts).await?;
+	encode(image, options)
+}
+
+// Images from an asset source can't be drawn until they've loaded, and images with neither data nor
+// a source, like ones in the query string that couldn't be decoded, can't be drawn at all
+pub(super) fn check_images(items: &[PageItems]) -> Result<(), String> {
+	for file in items.iter().filter_map(|item| item.file.as_ref()) {
+		if file.needs_loading() {
+			return Err(format!("{} hasn't loaded yet", file.name));
+		}
+		if file.data.is_empty() {
+			return Err(format!("{} has no image data, so it can't be exported", file.name));
+		}
+	}
+	Ok(())
+}
+
+// Render a surface to pixels. scale is on top of the scale needed to reach the target DPI
+// fonts are font files keyed by font family, for drawing text
+pub async fn render_surface(surface: &Surface, scale: f64, fonts: &HashMap<String, Vec<u8>>) -> Result<RgbaImage, String> {
+	let settings = &surface.canvas_settings;
+
+	// Output pixels per canvas unit, and how much bigger that is than the canvas on screen
+	let screen_scale = settings.export_scale() * scale;
+	let px_per_unit = settings.px_per_unit() * screen_scale;
+	let width = ((settings.full_width_units() * px_per_unit).round() as u32).max(1);
+	let height = ((settings.full_height_units() * px_per_unit).round() as u32).max(1);
+
+	let mut output = if settings.transparent_export {
+		RgbaImage::new(width, height)
+	} else {
+		render_background(settings, width, height).await?
+	};
+
+	// The first item is drawn on top, same as on the page
+	for item in surface.items.iter().rev() {
+		let area = item_area(item, settings);
+		let x = (area.x * px_per_unit).round() as i64;
+		let y = (area.y * px_per_unit).round() as i64;
+		let item_width = ((area.width * px_per_unit).round() as u32).max(1);
+		let item_height = ((area.height * px_per_unit).round() as u32).max(1);
+
+		if let Some(file) = &item.file {
+			let crop = item.fills_frame().then_some(&item.crop);
+			let layer = render_image(file, item_width, item_height, crop)?;
+			imageops::overlay(&mut output, &layer, x, y);
+		} else if let Some(text) = &item.text {
+			let font = fonts.get(&text.font_family).map(Vec::as_slice);
+			let layer = render_text(text, item_width, item_height, screen_scale, font)?;
+			imageops::overlay(&mut output, &layer, x, y);
+		}
+	}
+
+	apply_mask(&mut output, settings).await?;
+
+	// Cut the bleed off if it shouldn't be exported
+	if !settings.export_bleed && settings.bleed_mm > 0.0 {
+		let trim_area = settings.trim_area();
+		output = imageops::crop_imm(
+			&output,
+			(trim_area.x * px_per_unit).round() as u32,
+			(trim_area.y * px_per_unit).round() as u32,
+			((trim_area.width * px_per_unit).round() as u32).max(1),
+			((trim_area.height * px_per_unit).round() as u32).max(1),
+		).to_image();
+	}
+
+	Ok(output)
+}
+
+fn encode(image: RgbaImage, options: &ExportOptions) -> Result<Vec<u8>, String> {
+	let mut bytes = Cursor::new(Vec::new());
+	let (width, height) = image.dimensions();
+
+	let result = match options.format {
+		ExportFormat::Png => DynamicImage::ImageRgba8(image).write_to(&mut bytes, ImageFormat::Png),
+		ExportFormat::Jpeg => {
+			// JPEG has no alpha channel, so put the image on a white background first
+			let mut flattened = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+			imageops::overlay(&mut flattened, &image, 0, 0);
+			let flattened = DynamicImage::ImageRgba8(flattened).to_rgb8();
+			JpegEncoder::new_with_quality(&mut bytes, options.quality.unwrap_or(90).clamp(1, 100)).encode_image(&flattened)
+		}
+		ExportFormat::Webp => WebPEncoder::new_lossless(&mut bytes).encode(image.as_raw(), width, height, ExtendedColorType::Rgba8),
+		ExportFormat::Pdf | ExportFormat::Svg => return Err(format!("{} exports aren't rendered", options.format.extension())),
+	};
+
+	result.map_err(|err| format!("Failed to encode export: {}", err))?;
+	Ok(bytes.into_inner())
+}
+
+// Images in a placeholder are cropped to the part that shows in the frame, the same as on the page
+fn render_image(file: &FileDetails, width: u32, height: u32, crop: Option<&ImageCrop>) -> Result<RgbaImage, String> {
+	let image = image::load_from_memory(&file.data).map_err(|err| format!("Failed to decode image {}: {}", file.name, err))?.to_rgba8();
+	let Some(crop) = crop else {
+		return Ok(imageops::resize(&image, width, height, FilterType::CatmullRom));
+	};
+
+	// Work out which part of the image is in the frame before scaling, so only that part is scaled
+	let (x, y, layout_width, _) = crop.layout(width as f64, height as f64, image.width() as f64, image.height() as f64);
+	let image_px = image.width() as f64 / layout_width;
+	let crop_x = ((-x * image_px).round() as u32).min(image.width() - 1);
+	let crop_y = ((-y * image_px).round() as u32).min(image.height() - 1);
+	let crop_width = ((width as f64 * image_px).round() as u32).clamp(1, image.width() - crop_x);
+	let crop_height = ((height as f64 * image_px).round() as u32).clamp(1, image.height() - crop_y);
+
+	let visible = imageops::crop_imm(&image, crop_x, crop_y, crop_width, crop_height).to_image();
+	Ok(imageops::resize(&visible, width, height, FilterType::CatmullRom))
+}
+
+// Draw text the same way the text components show it: a filled box with the text in the top left,
+// wrapped like the textarea. Text is drawn in Rust when there's a font file for it
+fn render_text(text: &TextDetails, width: u32, height: u32, scale: f64, font: Option<&[u8]>) -> Result<RgbaImage, String> {
+	let font_size = text.font_size as f64 * scale;
+	let color = parse_color(&text.font_color).unwrap_or(Rgba([0, 0, 0, 255]));
+	let mut layer = match parse_color(&text.background_color) {
+		Some(background) => RgbaImage::from_pixel(width, height, background),
+		None => RgbaImage::new(width, height),
+	};
+
+	if let Some(face) = font.and_then(|data| Face::parse(data, 0).ok()) {
+		let coverage = raster::render_text(&face, &text.text, font_size, width, height);
+		raster::paint(&mut layer, &coverage, color, 0, 0);
+		return Ok(layer);
+	}
+
+	// Without a font file only the browser knows what the font looks like
+	let (_canvas, context) = new_canvas(width, height)?;
+	context.set_fill_style(&JsValue::from_str(&text.font_color));
+	context.set_font(&format!("{}px {}", font_size, text.font_family));
+	context.set_text_baseline("top");
+	let measure = |line: &str| context.measure_text(line).map(|metrics| metrics.width()).unwrap_or(0.0);
+	for (i, line) in wrap_text(&text.text, width as f64, measure).iter().enumerate() {
+		context.fill_text(line, 0.0, i as f64 * font_size * LINE_HEIGHT).map_err(js_error)?;
+	}
+
+	imageops::overlay(&mut layer, &canvas_pixels(&context, width, height)?, 0, 0);
+	Ok(layer)
+}
+
+async fn render_background(settings: &CanvasSettings, width: u32, height: u32) -> Result<RgbaImage, String> {
+	let mut output = match settings.background_color.as_deref().and_then(parse_color) {
+		Some(color) => RgbaImage::from_pixel(width, height, color),
+		None => RgbaImage::new(width, height),
+	};
+
+	if let Some(url) = &settings.background_image {
+		let image = load_scaled(url, width, height, true).await?;
+		imageops::overlay(&mut output, &image, 0, 0);
+	}
+
+	Ok(output)
+}
+
+// Multiply the alpha of every pixel by the alpha of the mask, stretched over the whole image
+async fn apply_mask(output: &mut RgbaImage, settings: &CanvasSettings) -> Result<(), String> {
+	let (width, height) = output.dimensions();
+	let mask: Vec<u8> = if let Some(path) = &settings.mask_path {
+		let mut outline = Outline::new();
+		outline.add_svg_path(path, width as f64 / settings.full_width_units(), height as f64 / settings.full_height_units())?;
+		outline.fill(width, height).into_raw()
+	} else if let Some(url) = &settings.mask_image {
+		load_scaled(url, width, height, false).await?.pixels().map(|pixel| pixel.0[3]).collect()
+	} else {
+		return Ok(());
+	};
+
+	for (pixel, mask_alpha) in output.pixels_mut().zip(mask) {
+		pixel.0[3] = ((pixel.0[3] as u16 * mask_alpha as u16) / 255) as u8;
+	}
+
+	Ok(())
+}
+
+// Load an image and scale it to width by height, either stretched or covering the whole area and
+// centered. Formats the image crate can't read are drawn by the browser instead
+async fn load_scaled(url: &str, width: u32, height: u32, cover: bool) -> Result<RgbaImage, String> {
+	let Ok(image) = image::load_from_memory(&fetch_bytes(url).await?) else {
+		return draw_scaled(url, width, height, cover).await;
+	};
+	let image = image.to_rgba8();
+	if !cover {
+		return Ok(imageops::resize(&image, width, height, FilterType::CatmullRom));
+	}
+
+	let scale = (width as f64 / image.width() as f64).max(height as f64 / image.height() as f64);
+	let draw_width = ((image.width() as f64 * scale).round() as u32).max(1);
+	let draw_height = ((image.height() as f64 * scale).round() as u32).max(1);
+	let scaled = imageops::resize(&image, draw_width, draw_height, FilterType::CatmullRom);
+
+	let mut output = RgbaImage::new(width, height);
+	imageops::overlay(&mut output, &scaled, (width as i64 - draw_width as i64) / 2, (height as i64 - draw_height as i64) / 2);
+	Ok(output)
+}
+
+async fn draw_scaled(url: &str, width: u32, height: u32, cover: bool) -> Result<RgbaImage, String> {
+	let (_canvas, context) = new_canvas(width, height)?;
+	let image = load_image(url).await?;
+	let image_width = image.natural_width() as f64;
+	let image_height = image.natural_height() as f64;
+	let scale = (width as f64 / image_width).max(height as f64 / image_height);
+	let (draw_width, draw_height) = if cover {
+		(image_width * scale, image_height * scale)
+	} else {
+		(width as f64, height as f64)
+	};
+	context.draw_image_with_html_image_element_and_dw_and_dh(
+		&image,
+		(width as f64 - draw_width) / 2.0,
+		(height as f64 - draw_height) / 2.0,
+		draw_width,
+		draw_height,
+	).map_err(js_error)?;
+
+	canvas_pixels(&context, width, height)
+}
+
+fn new_canvas(width: u32, height: u32) -> Result<(HtmlCanvasElement, CanvasRenderingContext2d), String> {
+	let document = web_sys::window().unwrap().document().unwrap();
+	let canvas: HtmlCanvasElement = document.create_element("canvas").map_err(js_error)?.unchecked_into();
+	canvas.set_width(width);
+	canvas.set_height(height);
+
+	let context: CanvasRenderingContext2d = canvas.get_context("2d")
+		.map_err(js_error)?
+		.ok_or("Canvas has no 2d context")?
+		.unchecked_into();
+	Ok((canvas, context))
+}
+
+fn canvas_pixels(context: &CanvasRenderingContext2d, width: u32, height: u32) -> Result<RgbaImage, String> {
+	let data = context.get_image_data(0.0, 0.0, width as f64, height as f64).map_err(js_error)?;
+	RgbaImage::from_raw(width, height, data.data().0).ok_or("Canvas returned the wrong number of pixels".to_string())
+}
+
+// Load an image from a URL so it can be drawn on a canvas
+async fn load_image(url: &str) -> Result<HtmlImageElement, String> {
+	let image = HtmlImageElement::new().map_err(js_error)?;
+	image.set_cross_origin(Some("anonymous"));
+
+	let loaded = js_sys::Promise::new(&mut |resolve, reject| {
+		image.set_onload(Some(&resolve));
+		image.set_onerror(Some(&reject));
+	});
+	image.set_src(url);
+
+	JsFuture::from(loaded).await.map_err(|_| format!("Failed to load image: {}", url))?;
+	Ok(image)
+}
+
+// Download the font files for the surface's text, keyed by font family
+async fn load_fonts(surface: &Surface, options: &ExportOptions) -> Result<HashMap<String, Vec<u8>>, String> {
+	let mut fonts = HashMap::new();
+
+	for item in &surface.items {
+		let Some(text) = &item.text else { continue };
+		if fonts.contains_key(&text.font_family) {
+			continue;
+		}
+		if let Some(url) = options.fonts.get(&text.font_family) {
+			fonts.insert(text.font_family.clone(), fetch_bytes(url).await?);
+		}
+	}
+
+	Ok(fonts)
+}
+
+// Download the fonts used by the surface's text, and its background image
+async fn load_pdf_resources(surface: &Surface, options: &ExportOptions) -> Result<PdfResources, String> {
+	let mut resources = PdfResources {
+		fonts: load_fonts(surface, options).await?,
+		..Default::default()
+	};
+
+	if let Some(url) = &surface.canvas_settings.background_image {
+		resources.images.insert(url.clone(), fetch_bytes(url).await?);
+	}
+
+	Ok(resources)
+}
+
+// Download the background and mask images, so they can be embedded in an SVG
+async fn load_svg_images(settings: &CanvasSettings) -> Result<HashMap<String, Vec<u8>>, String> {
+	let background = settings.background_image.as_ref().filter(|_| !settings.transparent_export);
+	let mask = settings.mask_image.as_ref().filter(|_| settings.mask_path.is_none());
+
+	let mut images = HashMap::new();
+	for url in background.into_iter().chain(mask) {
+		images.insert(url.clone(), fetch_bytes(url).await?);
+	}
+	Ok(images)
+}
+
+pub(super) async fn fetch_bytes(url: &str) -> Result<Vec<u8>, String> {
+	let window = web_sys::window().unwrap();
+	let response: Response = JsFuture::from(window.fetch_with_str(url))
+		.await
+		.map_err(|_| format!("Failed to load {}", url))?
+		.unchecked_into();
+	if !response.ok() {
+		return Err(format!("Failed to load {}: {}", url, response.status()));
+	}
+
+	let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?).await.map_err(js_error)?;
+	Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+pub(super) fn js_error(err: JsValue) -> String {
+	err.as_string().unwrap_or_else(|| format!("{:?}", err))
+}
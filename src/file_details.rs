@@ -3,8 +3,40 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub struct FileDetails {
 	pub name: String,
+	// Worked out from the image if an image with a source doesn't give one
+	#[serde(default)]
 	pub file_type: String,
+	// Empty while an image with a source is loading. Saved designs leave it out for those images
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
 	pub data: Vec<u8>,
+	// 0 until an image with a source has loaded, if they weren't given
+	#[serde(default)]
 	pub width: i32,
+	#[serde(default)]
 	pub height: i32,
+	// Asset id or URL the image is loaded from, instead of being stored in the design. See assets.rs
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub source: Option<String>,
+}
+
+impl FileDetails {
+	// Size the image is shown at when only one dimension is given, keeping its aspect ratio. None if
+	// neither is given, or the image doesn't have a size yet
+	pub fn scaled_size(&self, width: Option<f64>, height: Option<f64>) -> Option<(f64, f64)> {
+		if self.width <= 0 || self.height <= 0 {
+			return None;
+		}
+		let aspect_ratio = self.width as f64 / self.height as f64;
+		match (width, height) {
+			(Some(width), Some(height)) => Some((width, height)),
+			(Some(width), None) => Some((width, width / aspect_ratio)),
+			(None, Some(height)) => Some((height * aspect_ratio, height)),
+			(None, None) => None,
+		}
+	}
+
+	// Whether the image still has to be loaded from its source
+	pub fn needs_loading(&self) -> bool {
+		self.source.is_some() && self.data.is_empty()
+	}
 }
@@ -0,0 +1,299 @@
+// Cuts TrueType fonts down to the glyphs a PDF uses, so it doesn't carry the whole font file
+// Glyph ids stay the same, so text encoded with the full font still works: unused glyphs are left
+// empty rather than removed. Only the tables PDF readers need for TrueType fonts are kept
+// Fonts with CFF outlines aren't subset, since they have no glyf table
+use std::collections::BTreeSet;
+
+// Tables a TrueType font program in a PDF needs. cvt, fpgm and prep are only kept if the font has them
+static KEPT_TABLES: [&[u8; 4]; 9] = [b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep"];
+
+// Flags of a component in a composite glyph, which say how long it is
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+struct Table<'a> {
+	tag: [u8; 4],
+	data: &'a [u8],
+}
+
+// Returns None if the font can't be subset, in which case the whole font should be used
+pub fn subset_font(font: &[u8], glyphs: impl IntoIterator<Item = u16>) -> Option<Vec<u8>> {
+	let tables = read_tables(font)?;
+	let table = |tag: &[u8; 4]| tables.iter().find(|table| &table.tag == tag).map(|table| table.data);
+	let (head, maxp, loca, glyf) = (table(b"head")?, table(b"maxp")?, table(b"loca")?, table(b"glyf")?);
+
+	let glyph_count = read_u16(maxp, 4)? as usize;
+	let long_offsets = read_u16(head, 50)? == 1;
+	let offsets: Vec<usize> = (0..=glyph_count)
+		.map(|i| if long_offsets { read_u32(loca, i * 4).map(|offset| offset as usize) } else { read_u16(loca, i * 2).map(|offset| offset as usize * 2) })
+		.collect::<Option<_>>()?;
+	let glyph_data = |glyph: usize| offsets.get(glyph + 1).and_then(|end| glyf.get(offsets[glyph]..*end));
+
+	// .notdef is always kept, and so is every glyph a composite glyph is made from
+	let mut kept = BTreeSet::new();
+	let mut pending = vec![0];
+	pending.extend(glyphs.into_iter().filter(|glyph| (*glyph as usize) < glyph_count));
+	while let Some(glyph) = pending.pop() {
+		if kept.insert(glyph) {
+			pending.extend(components(glyph_data(glyph as usize)?)?.into_iter().filter(|glyph| !kept.contains(glyph)));
+		}
+	}
+
+	// Glyphs are 4 byte aligned, so the offsets are written in the long format
+	let mut new_glyf = Vec::new();
+	let mut new_loca = Vec::with_capacity((glyph_count + 1) * 4);
+	for glyph in 0..glyph_count {
+		new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+		if kept.contains(&(glyph as u16)) {
+			new_glyf.extend_from_slice(glyph_data(glyph)?);
+			pad(&mut new_glyf);
+		}
+	}
+	new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+	let mut new_head = head.to_vec();
+	new_head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+	let mut subset: Vec<([u8; 4], Vec<u8>)> = KEPT_TABLES.iter()
+		.filter_map(|tag| {
+			let data = match *tag {
+				b"glyf" => new_glyf.clone(),
+				b"loca" => new_loca.clone(),
+				b"head" => new_head.clone(),
+				_ => table(tag)?.to_vec(),
+			};
+			Some((**tag, data))
+		})
+		.collect();
+	subset.sort_by_key(|(tag, _)| *tag);
+	Some(write_font(&mut subset))
+}
+
+fn read_tables(font: &[u8]) -> Option<Vec<Table<'_>>> {
+	// Only plain TrueType fonts, not collections or CFF fonts
+	let version = read_u32(font, 0)?;
+	if version != 0x00010000 && version != u32::from_be_bytes(*b"true") {
+		return None;
+	}
+
+	let table_count = read_u16(font, 4)? as usize;
+	(0..table_count)
+		.map(|i| {
+			let record = 12 + i * 16;
+			let tag = font.get(record..record + 4)?.try_into().ok()?;
+			let offset = read_u32(font, record + 8)? as usize;
+			let length = read_u32(font, record + 12)? as usize;
+			Some(Table { tag, data: font.get(offset..offset.checked_add(length)?)? })
+		})
+		.collect()
+}
+
+// Glyphs a composite glyph is made from. Simple and empty glyphs don't have any
+fn components(data: &[u8]) -> Option<Vec<u16>> {
+	let mut components = Vec::new();
+	if data.is_empty() || read_u16(data, 0)? as i16 >= 0 {
+		return Some(components);
+	}
+
+	// Components start after the glyph's header
+	let mut offset = 10;
+	loop {
+		let flags = read_u16(data, offset)?;
+		components.push(read_u16(data, offset + 2)?);
+
+		offset += 4 + if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+		if flags & WE_HAVE_A_SCALE != 0 {
+			offset += 2;
+		} else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+			offset += 4;
+		} else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+			offset += 8;
+		}
+
+		if flags & MORE_COMPONENTS == 0 {
+			return Some(components);
+		}
+	}
+}
+
+// Write the tables as a font file, with the checksums filled in
+fn write_font(tables: &mut [([u8; 4], Vec<u8>)]) -> Vec<u8> {
+	let table_count = tables.len() as u16;
+	let entry_selector = 15 - table_count.max(1).leading_zeros() as u16;
+	let search_range = (1 << entry_selector) * 16;
+
+	let mut font = Vec::new();
+	font.extend_from_slice(&0x00010000u32.to_be_bytes());
+	for value in [table_count, search_range, entry_selector, table_count * 16 - search_range] {
+		font.extend_from_slice(&value.to_be_bytes());
+	}
+
+	// The font's checksum is worked out with the head table's adjustment set to 0
+	let mut head_offset = None;
+	let mut offset = 12 + tables.len() * 16;
+	for (tag, data) in tables.iter_mut() {
+		if tag == b"head" {
+			data[8..12].fill(0);
+			head_offset = Some(offset);
+		}
+		font.extend_from_slice(tag);
+		font.extend_from_slice(&checksum(data).to_be_bytes());
+		font.extend_from_slice(&(offset as u32).to_be_bytes());
+		font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+		offset += data.len().div_ceil(4) * 4;
+	}
+	for (_, data) in tables.iter() {
+		font.extend_from_slice(data);
+		pad(&mut font);
+	}
+
+	if let Some(head_offset) = head_offset {
+		let adjustment = 0xB1B0AFBAu32.wrapping_sub(checksum(&font));
+		font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+	}
+	font
+}
+
+// Sum of the data as big endian u32s, padded with zeroes
+fn checksum(data: &[u8]) -> u32 {
+	data.chunks(4).fold(0u32, |sum, chunk| {
+		let mut word = [0; 4];
+		word[..chunk.len()].copy_from_slice(chunk);
+		sum.wrapping_add(u32::from_be_bytes(word))
+	})
+}
+
+fn pad(data: &mut Vec<u8>) {
+	data.resize(data.len().div_ceil(4) * 4, 0);
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+	Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+	Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use ttf_parser::{Face, GlyphId};
+	use super::*;
+
+	// A triangle, padded to an even length so the short loca format can point past it
+	fn simple_glyph() -> Vec<u8> {
+		let mut glyph = Vec::new();
+		for value in [1i16, 0, 0, 100, 100, 2, 0] {
+			glyph.extend_from_slice(&value.to_be_bytes());
+		}
+		glyph.extend_from_slice(&[1, 1, 1]);
+		for value in [0i16, 100, -50, 0, 0, 100] {
+			glyph.extend_from_slice(&value.to_be_bytes());
+		}
+		glyph.push(0);
+		glyph
+	}
+
+	// Glyph 2 placed at the origin
+	fn composite_glyph() -> Vec<u8> {
+		let mut glyph = Vec::new();
+		for value in [-1i16, 0, 0, 100, 100, 0x0003, 2, 0, 0] {
+			glyph.extend_from_slice(&value.to_be_bytes());
+		}
+		glyph
+	}
+
+	// A TrueType font with an empty .notdef, triangles for A and B, and C made from B
+	pub(crate) fn test_font() -> Vec<u8> {
+		let glyphs = [Vec::new(), simple_glyph(), simple_glyph(), composite_glyph()];
+		let mut glyf = Vec::new();
+		let mut loca = Vec::new();
+		for glyph in &glyphs {
+			loca.extend_from_slice(&(glyf.len() as u16 / 2).to_be_bytes());
+			glyf.extend_from_slice(glyph);
+		}
+		loca.extend_from_slice(&(glyf.len() as u16 / 2).to_be_bytes());
+
+		let mut head = vec![0; 54];
+		head[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+		head[12..16].copy_from_slice(&0x5F0F3CF5u32.to_be_bytes());
+		head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+		head[40..44].copy_from_slice(&[0, 100, 0, 100]);
+
+		let mut hhea = vec![0; 36];
+		hhea[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+		hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+		hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+		hhea[34..36].copy_from_slice(&4u16.to_be_bytes());
+
+		let mut maxp = 0x00005000u32.to_be_bytes().to_vec();
+		maxp.extend_from_slice(&4u16.to_be_bytes());
+
+		let hmtx: Vec<u8> = (0..4).flat_map(|_| [500u16.to_be_bytes(), 0u16.to_be_bytes()].concat()).collect();
+
+		// Format 4 cmap mapping A to C onto glyphs 1 to 3
+		let mut cmap = Vec::new();
+		for value in [0u16, 1, 3, 1, 0, 12, 4, 32, 0, 4, 4, 1, 0, 0x43, 0xFFFF, 0, 0x41, 0xFFFF, 1u16.wrapping_sub(0x41), 1, 0, 0] {
+			cmap.extend_from_slice(&value.to_be_bytes());
+		}
+
+		write_font(&mut [
+			(*b"cmap", cmap),
+			(*b"glyf", glyf),
+			(*b"head", head),
+			(*b"hhea", hhea),
+			(*b"hmtx", hmtx),
+			(*b"loca", loca),
+			(*b"maxp", maxp),
+		])
+	}
+
+	fn has_outline(face: &Face, glyph: u16) -> bool {
+		face.glyph_bounding_box(GlyphId(glyph)).is_some()
+	}
+
+	#[test]
+	fn keeps_only_used_glyphs() {
+		let font = test_font();
+		let subset = subset_font(&font, [1]).unwrap();
+		let face = Face::parse(&subset, 0).unwrap();
+
+		assert_eq!(face.number_of_glyphs(), 4, "Glyph ids should stay the same");
+		assert!(has_outline(&face, 1));
+		assert!(!has_outline(&face, 2));
+		assert!(!has_outline(&face, 3));
+		assert_eq!(face.glyph_hor_advance(GlyphId(1)), Some(500));
+		assert!(subset.len() < font.len());
+	}
+
+	#[test]
+	fn keeps_composite_components() {
+		let subset = subset_font(&test_font(), [3]).unwrap();
+		let face = Face::parse(&subset, 0).unwrap();
+
+		assert!(has_outline(&face, 3));
+		assert!(has_outline(&face, 2));
+		assert!(!has_outline(&face, 1));
+	}
+
+	#[test]
+	fn ignores_glyphs_the_font_does_not_have() {
+		let subset = subset_font(&test_font(), [1, 200]).unwrap();
+		assert_eq!(Face::parse(&subset, 0).unwrap().number_of_glyphs(), 4);
+	}
+
+	#[test]
+	fn checksum_adjustment() {
+		assert_eq!(checksum(&subset_font(&test_font(), [1]).unwrap()), 0xB1B0AFBA);
+	}
+
+	#[test]
+	fn cff_fonts_are_not_subset() {
+		let mut font = test_font();
+		font[0..4].copy_from_slice(b"OTTO");
+		assert!(subset_font(&font, [1]).is_none());
+	}
+}
@@ -1,9 +1,13 @@
 // Code for a generic div that can be moved around the screen by dragging it with the mouse
 // Can also be resized
+// While it has focus, the arrow keys move it (10 pixels at a time with Shift) and Alt and the arrow keys
+// resize it. Enter starts editing it, and Escape stops
 
 use web_sys::{MouseEvent, HtmlElement, console, window};
 use yew::prelude::*;
 use wasm_bindgen::prelude::*;
+use crate::app::clipboard::in_text_field;
+use crate::app::selection_focus::use_selection_focus;
 
 #[wasm_bindgen]
 extern "C" {
@@ -30,6 +34,24 @@ pub struct MouseMoveProps {
 	pub start_x: Option<i32>,
 	#[prop_or(None)]
 	pub start_y: Option<i32>,
+	#[prop_or_default]
+	pub ondelete: Callback<()>,
+	// Called with the new (x, y, width, height) when the user finishes moving or resizing the div.
+	// Like the other geometry props, these are in screen pixels, and the app converts them to canvas
+	// units
+	#[prop_or_default]
+	pub onchange: Callback<(i32, i32, i32, i32)>,
+	// Called when the user clicks the div, to move it, resize it or edit it
+	#[prop_or_default]
+	pub onselect: Callback<()>,
+	// Called when Enter is pressed on the div
+	#[prop_or_default]
+	pub onedit: Callback<()>,
+	// The div takes focus when it's selected, so it can be used with the keyboard. The arrow keys
+	// move or resize it by NUDGE_STEP or LARGE_NUDGE_STEP screen pixels, so on a canvas in mm or
+	// inches the step in canvas units depends on the canvas' display_width
+	#[prop_or(false)]
+	pub selected: bool,
 }
 
 #[derive(Copy, Clone)]
@@ -85,6 +107,9 @@ impl std::fmt::Display for ResizeDirection {
 
 static DEFAULT_WIDTH: i32 = 250;
 static DEFAULT_HEIGHT: i32 = 250;
+// How far the arrow keys move or resize the div, in screen pixels rather than canvas units
+static NUDGE_STEP: i32 = 1;
+static LARGE_NUDGE_STEP: i32 = 10;
 
 #[function_component]
 pub fn MouseMoveComponent(props: &MouseMoveProps) -> Html {
@@ -122,6 +147,25 @@ pub fn MouseMoveComponent(props: &MouseMoveProps) -> Html {
 	let mousex = use_state(|| props.start_x.unwrap_or(0));
 	let mousey = use_state(|| props.start_y.unwrap_or(0));
 
+	// Follows the props, so changes to the item from the host page show up. Moving or resizing the div
+	// sends its new geometry to the parent, which comes back here as the same values
+	{
+		let mousex = mousex.clone();
+		let mousey = mousey.clone();
+		let width = width.clone();
+		let height = height.clone();
+		use_effect_with((props.start_x, props.start_y, props.width, props.height), move |(start_x, start_y, new_width, new_height)| {
+			mousex.set(start_x.unwrap_or(0));
+			mousey.set(start_y.unwrap_or(0));
+			if let Some(new_width) = new_width {
+				width.set(*new_width);
+			}
+			if let Some(new_height) = new_height {
+				height.set(*new_height);
+			}
+		});
+	}
+
 	// Saves where the mouse was clicked for resizing purposes
 	let clickx = use_state(|| 0);
 	let clicky = use_state(|| 0);
@@ -148,12 +192,69 @@ pub fn MouseMoveComponent(props: &MouseMoveProps) -> Html {
 	let z_index = use_state(|| 1);
 	let old_z_index = use_state(|| 1);
 
+	use_selection_focus(div_node_ref.clone(), props.selected);
+
 	let onkeydown = {
 		let hidden = hidden.clone();
+		let mousex = mousex.clone();
+		let mousey = mousey.clone();
+		let width = width.clone();
+		let height = height.clone();
+		let div_node_ref = div_node_ref.clone();
+		let ondelete = props.ondelete.clone();
+		let onchange = props.onchange.clone();
+		let onedit = props.onedit.clone();
 		move |event: KeyboardEvent| {
-			if !*hidden && event.key() == "Delete" {
-				hidden.set(true);
+			if *hidden {
+				return;
 			}
+
+			// Keys pressed while editing are left to the text field, other than Escape to stop editing
+			if in_text_field(event.target()) {
+				if event.key() == "Escape" {
+					event.stop_propagation();
+					if let Some(element) = div_node_ref.cast::<HtmlElement>() {
+						focus_element(&element);
+					}
+				}
+				return;
+			}
+
+			let step = if event.shift_key() { LARGE_NUDGE_STEP } else { NUDGE_STEP };
+			let (dx, dy) = match event.key().as_str() {
+				"Delete" => {
+					hidden.set(true);
+					ondelete.emit(());
+					return;
+				}
+				"Enter" => {
+					event.prevent_default();
+					onedit.emit(());
+					return;
+				}
+				"ArrowLeft" => (-step, 0),
+				"ArrowRight" => (step, 0),
+				"ArrowUp" => (0, -step),
+				"ArrowDown" => (0, step),
+				_ => return,
+			};
+			// Otherwise the page scrolls
+			event.prevent_default();
+
+			let (mut x, mut y, mut new_width, mut new_height) = (*mousex, *mousey, *width, *height);
+			if event.alt_key() {
+				new_width = (new_width + dx).max(1);
+				new_height = (new_height + dy).max(1);
+			} else {
+				x += dx;
+				y += dy;
+			}
+
+			mousex.set(x);
+			mousey.set(y);
+			width.set(new_width);
+			height.set(new_height);
+			onchange.emit((x, y, new_width, new_height));
 		}
 	};
 
@@ -209,11 +310,15 @@ pub fn MouseMoveComponent(props: &MouseMoveProps) -> Html {
 		let trigger = trigger.clone();
 
 		let div_node_ref = div_node_ref.clone();
+		let onselect = props.onselect.clone();
 		move |event: MouseEvent| {
 			if event.button() != 0 {
 				return;
 			}
 
+			// Clicks on the resizers bubble up to here, so this covers resizing as well
+			onselect.emit(());
+
 			if *resizing || *on_edge || *dragging || *hidden {
 				return;
 			}
@@ -245,10 +350,15 @@ pub fn MouseMoveComponent(props: &MouseMoveProps) -> Html {
 		let mousey = mousey.clone();
 		let div_node_ref = div_node_ref.clone();
 		let trigger = trigger.clone();
+		let onchange = props.onchange.clone();
 		move |_: MouseEvent| {
 			mousex.set(div_node_ref.cast::<HtmlElement>().unwrap().offset_left());
 			mousey.set(div_node_ref.cast::<HtmlElement>().unwrap().offset_top());
 
+			if *dragging || *resizing {
+				emit_geometry(&div_node_ref, &onchange);
+			}
+
 			dragging.set(false);
 			resizing.set(false);
 
@@ -300,8 +410,11 @@ pub fn MouseMoveComponent(props: &MouseMoveProps) -> Html {
 	// Unclick function to be attached to window event handler
 	let on_resizer_mouse_up = {
 		let resizing = resizing.clone();
+		let div_node_ref = div_node_ref.clone();
+		let onchange = props.onchange.clone();
 		move |_: MouseEvent| {
 			resizing.set(false);
+			emit_geometry(&div_node_ref, &onchange);
 			
 			let window = window().unwrap();
 
@@ -484,6 +597,13 @@ pub fn MouseMoveComponent(props: &MouseMoveProps) -> Html {
 	}
 }
 
+// Report the current position and size of the div to the parent
+fn emit_geometry(div_node_ref: &NodeRef, onchange: &Callback<(i32, i32, i32, i32)>) {
+	if let Some(element) = div_node_ref.cast::<HtmlElement>() {
+		onchange.emit((element.offset_left(), element.offset_top(), element.client_width(), element.client_height()));
+	}
+}
+
 fn get_resize_move_function(
 	drag_start_left: i32,
 	drag_start_top: i32,
@@ -0,0 +1,286 @@
+// Lets a parent page on another origin control the editor when it's embedded in an iframe
+// Every message is an object with protocol: "web-image-editor" and the protocol version. Requests
+// from the parent page have a type (see HostMessage) and an optional id, and are answered with a
+// response or error with the same id. Editor events the parent page subscribes to are sent as
+// event messages
+// Only messages from the editor's own origin and the origins given to run_app are listened to
+use std::cell::RefCell;
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{console, MessageEvent, Window};
+use crate::page_items::PageItems;
+use crate::mockup::MockupTemplate;
+use super::CanvasBackground;
+use super::editor_handle::EditorHandle;
+use super::export::ExportOptions;
+use super::surface::SurfaceDesign;
+
+pub static PROTOCOL: &str = "web-image-editor";
+// Bump this when a message changes in a way older parent pages wouldn't understand
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Allow-list entry that lets any origin control the editor
+static ANY_ORIGIN: &str = "*";
+
+// The fields every message has
+#[derive(Deserialize)]
+struct Envelope {
+	protocol: String,
+	version: u32,
+	// Anything the parent page wants to match responses up with
+	#[serde(default)]
+	id: serde_json::Value,
+}
+
+// Requests from the parent page. These match the EditorHandle functions of the same name
+// Items are given as item_id, since id is the request's own id
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum HostMessage {
+	AddItem {
+		item: PageItems,
+		#[serde(default)]
+		surface: Option<String>,
+	},
+	// force changes or removes template items their role protects (see EditorHandle::update_item)
+	UpdateItem {
+		item_id: u32,
+		changes: serde_json::Value,
+		#[serde(default)]
+		force: bool,
+	},
+	SetDesign {
+		design: Vec<SurfaceDesign>,
+	},
+	RemoveItem {
+		item_id: u32,
+		#[serde(default)]
+		force: bool,
+	},
+	SetCanvasBackground {
+		background: CanvasBackground,
+		#[serde(default)]
+		surface: Option<String>,
+	},
+	UpdateSettings {
+		settings: serde_json::Value,
+		#[serde(default)]
+		surface: Option<String>,
+	},
+	SetMockups {
+		mockups: Vec<MockupTemplate>,
+	},
+	Export {
+		#[serde(default)]
+		options: ExportOptions,
+		#[serde(default)]
+		surface: Option<String>,
+	},
+	GetState,
+	GetDesignLink {
+		#[serde(default)]
+		url: Option<String>,
+	},
+	Validate,
+	// Start or stop sending these editor events to the parent page
+	Subscribe {
+		events: Vec<String>,
+	},
+	Unsubscribe {
+		events: Vec<String>,
+	},
+}
+
+// Messages sent to the parent page
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum EditorMessage {
+	// The editor has started and is listening for messages
+	Ready,
+	Response {
+		id: serde_json::Value,
+		#[serde(with = "serde_wasm_bindgen::preserve")]
+		result: JsValue,
+	},
+	Error {
+		id: serde_json::Value,
+		error: String,
+	},
+	Event {
+		name: String,
+		#[serde(with = "serde_wasm_bindgen::preserve")]
+		detail: JsValue,
+	},
+}
+
+// An editor event being sent to a parent page
+struct Subscription {
+	name: String,
+	target: Window,
+	origin: String,
+	callback: js_sys::Function,
+}
+
+struct MessageListener {
+	handle: EditorHandle,
+	own_origin: String,
+	allowed_origins: Vec<String>,
+	subscriptions: RefCell<Vec<Subscription>>,
+}
+
+// Start listening for messages, and let the parent page know the editor is ready for them
+pub fn listen(handle: &EditorHandle, allowed_origins: Vec<String>) {
+	let window = web_sys::window().unwrap();
+	let listener = Rc::new(MessageListener {
+		handle: handle.clone(),
+		own_origin: window.location().origin().unwrap_or_default(),
+		allowed_origins,
+		subscriptions: RefCell::new(Vec::new()),
+	});
+
+	let callback = {
+		let listener = listener.clone();
+		Closure::<dyn Fn(MessageEvent)>::new(move |event: MessageEvent| {
+			let origin = event.origin();
+			if !listener.is_allowed(&origin) {
+				return;
+			}
+			// Windows on other origins aren't instances of this window's Window, so this can't be checked
+			let Some(target) = event.source().map(|source| source.unchecked_into::<Window>()) else {
+				return;
+			};
+
+			let listener = listener.clone();
+			wasm_bindgen_futures::spawn_local(async move {
+				listener.respond(event.data(), target, origin).await;
+			});
+		})
+	};
+	if window.add_event_listener_with_callback("message", callback.as_ref().unchecked_ref()).is_err() {
+		console::error_1(&"Failed to listen for messages".into());
+	}
+	// Listens for as long as the page is open
+	callback.forget();
+
+	if let Ok(Some(parent)) = window.parent() {
+		if JsValue::from(&parent) != JsValue::from(&window) {
+			for origin in listener.allowed_origins.iter().chain(std::iter::once(&listener.own_origin)) {
+				post(&parent, origin, &EditorMessage::Ready);
+			}
+		}
+	}
+}
+
+impl MessageListener {
+	fn is_allowed(&self, origin: &str) -> bool {
+		origin == self.own_origin || self.allowed_origins.iter().any(|allowed| allowed == ANY_ORIGIN || allowed == origin)
+	}
+
+	async fn respond(&self, data: JsValue, target: Window, origin: String) {
+		// Other scripts use messages too, so anything that isn't for the editor is ignored
+		let Ok(envelope) = serde_wasm_bindgen::from_value::<Envelope>(data.clone()) else {
+			return;
+		};
+		if envelope.protocol != PROTOCOL {
+			return;
+		}
+
+		let result = if envelope.version > PROTOCOL_VERSION {
+			Err(format!("Protocol version {} isn't supported. The editor supports up to version {}", envelope.version, PROTOCOL_VERSION))
+		} else {
+			match serde_wasm_bindgen::from_value::<HostMessage>(data) {
+				Ok(message) => self.run(message, &target, &origin).await
+					.map_err(|err| err.as_string().unwrap_or_else(|| format!("{:?}", err))),
+				Err(err) => Err(format!("Invalid message: {}", err)),
+			}
+		};
+
+		let response = match result {
+			Ok(result) => EditorMessage::Response { id: envelope.id, result },
+			Err(error) => EditorMessage::Error { id: envelope.id, error },
+		};
+		post(&target, &origin, &response);
+	}
+
+	async fn run(&self, message: HostMessage, target: &Window, origin: &str) -> Result<JsValue, JsValue> {
+		let handle = &self.handle;
+		match message {
+			HostMessage::AddItem { item, surface } => handle.add_page_item(item, surface).map(JsValue::from),
+			HostMessage::UpdateItem { item_id, changes, force } => handle.merge_item(item_id, changes, force).map(|_| JsValue::UNDEFINED),
+			HostMessage::SetDesign { design } => handle.replace_design(design).map(|_| JsValue::UNDEFINED),
+			HostMessage::RemoveItem { item_id, force } => handle.remove_item(item_id, Some(force)).map(|_| JsValue::UNDEFINED),
+			HostMessage::SetCanvasBackground { background, surface } => handle.set_background(background, surface).map(|_| JsValue::UNDEFINED),
+			HostMessage::UpdateSettings { settings, surface } => handle.merge_settings(settings, surface).map(|_| JsValue::UNDEFINED),
+			HostMessage::SetMockups { mockups } => {
+				handle.set_mockup_templates(mockups);
+				Ok(JsValue::UNDEFINED)
+			}
+			HostMessage::Export { options, surface } => JsFuture::from(handle.export_with(options, surface)?).await,
+			HostMessage::GetState => handle.get_state(),
+			HostMessage::GetDesignLink { url } => handle.get_design_link(url).map(JsValue::from),
+			HostMessage::Validate => handle.validate(),
+			HostMessage::Subscribe { events } => {
+				for name in events {
+					self.subscribe(name, target, origin);
+				}
+				Ok(JsValue::UNDEFINED)
+			}
+			HostMessage::Unsubscribe { events } => {
+				self.subscriptions.borrow_mut().retain(|subscription| {
+					let matches = events.contains(&subscription.name) && subscription.origin == origin && JsValue::from(&subscription.target) == JsValue::from(target);
+					if matches {
+						handle.events().off(&subscription.name, &subscription.callback);
+					}
+					!matches
+				});
+				Ok(JsValue::UNDEFINED)
+			}
+		}
+	}
+
+	fn subscribe(&self, name: String, target: &Window, origin: &str) {
+		let mut subscriptions = self.subscriptions.borrow_mut();
+		let subscribed = subscriptions.iter().any(|subscription| {
+			subscription.name == name && subscription.origin == origin && JsValue::from(&subscription.target) == JsValue::from(target)
+		});
+		if subscribed {
+			return;
+		}
+
+		let callback = {
+			let name = name.clone();
+			let target = target.clone();
+			let origin = origin.to_string();
+			Closure::<dyn Fn(JsValue)>::new(move |detail: JsValue| {
+				post(&target, &origin, &EditorMessage::Event { name: name.clone(), detail });
+			})
+		};
+		let callback: js_sys::Function = callback.into_js_value().unchecked_into();
+
+		self.handle.events().on(name.clone(), callback.clone());
+		subscriptions.push(Subscription {
+			name,
+			target: target.clone(),
+			origin: origin.to_string(),
+			callback,
+		});
+	}
+}
+
+fn post(target: &Window, origin: &str, message: &EditorMessage) {
+	let message = match serde_wasm_bindgen::to_value(message) {
+		Ok(message) => message,
+		Err(err) => {
+			console::error_1(&format!("Failed to serialize message: {}", err).into());
+			return;
+		}
+	};
+	let _ = js_sys::Reflect::set(&message, &"protocol".into(), &PROTOCOL.into());
+	let _ = js_sys::Reflect::set(&message, &"version".into(), &PROTOCOL_VERSION.into());
+
+	if let Err(err) = target.post_message(&message, origin) {
+		console::error_2(&"Failed to send message:".into(), &err);
+	}
+}
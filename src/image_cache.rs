@@ -0,0 +1,29 @@
+// Cache of object URLs for image items
+// Turning the image bytes into a URL is expensive, so it's only done once per item and the URL is
+// shared by every component that draws that item. The URL is revoked when the item is released
+use std::cell::RefCell;
+use std::collections::HashMap;
+use gloo::file::{Blob, ObjectUrl};
+use crate::file_details::FileDetails;
+
+thread_local! {
+	// ObjectUrl revokes itself when dropped, so removing it from the map is all that's needed
+	static OBJECT_URLS: RefCell<HashMap<u32, ObjectUrl>> = RefCell::new(HashMap::new());
+}
+
+// Get the object URL for an item's image, creating it if this is the first time it's been asked for
+pub fn object_url(item_id: u32, file: &FileDetails) -> String {
+	OBJECT_URLS.with(|urls| {
+		urls.borrow_mut()
+			.entry(item_id)
+			.or_insert_with(|| ObjectUrl::from(Blob::new_with_options(file.data.as_slice(), Some(&file.file_type))))
+			.to_string()
+	})
+}
+
+// Revoke the object URL of an item. Call this when the item is removed from the page
+pub fn release(item_id: u32) {
+	OBJECT_URLS.with(|urls| {
+		urls.borrow_mut().remove(&item_id);
+	});
+}
@@ -0,0 +1,249 @@
+// A template placeholder the customer puts their photo in
+// The photo covers the frame, and can be dragged to pan it or scrolled over to zoom it. Image files
+// dropped on the frame replace the photo
+// While the frame has focus, the arrow keys pan the photo (10 pixels at a time with Shift), + and -
+// zoom it, and Enter chooses a photo for it
+
+use yew::prelude::*;
+use web_sys::FileList;
+use crate::file_details::FileDetails;
+use crate::image_cache;
+use crate::page_items::ImageCrop;
+use super::assets::AssetStatus;
+use super::selection_focus::use_selection_focus;
+
+// Furthest a photo can be zoomed in, on top of covering the frame
+static MAX_ZOOM: f64 = 8.0;
+// How much each step of the mouse wheel zooms
+static ZOOM_STEP: f64 = 1.1;
+// How far the arrow keys pan the photo, in pixels
+static PAN_STEP: i32 = 1;
+static LARGE_PAN_STEP: i32 = 10;
+
+#[derive(PartialEq, Properties)]
+pub struct ImageFrameProps {
+	#[prop_or_default]
+	pub id: AttrValue,
+	#[prop_or_default]
+	pub class: AttrValue,
+	#[prop_or_default]
+	pub children: Children,
+	pub x: i32,
+	pub y: i32,
+	pub width: i32,
+	pub height: i32,
+	pub item_id: u32,
+	#[prop_or_default]
+	pub file: Option<FileDetails>,
+	#[prop_or_default]
+	pub crop: ImageCrop,
+	#[prop_or(AssetStatus::Ready)]
+	pub status: AssetStatus,
+	// Shown while there's no photo, or it's loading or failed to load
+	#[prop_or_default]
+	pub text: AttrValue,
+	// Whether the photo can be panned, zoomed and replaced
+	#[prop_or(true)]
+	pub editable: bool,
+	#[prop_or_default]
+	pub onselect: Callback<()>,
+	// The frame takes focus when it's selected, so it can be used with the keyboard
+	#[prop_or(false)]
+	pub selected: bool,
+	// Called when Enter is pressed on the frame
+	#[prop_or_default]
+	pub onedit: Callback<()>,
+	// Called with the new crop when the user finishes panning, and every step of zooming
+	#[prop_or_default]
+	pub oncropchange: Callback<ImageCrop>,
+	#[prop_or_default]
+	pub ondropfiles: Callback<FileList>,
+}
+
+#[function_component]
+pub fn ImageFrameComponent(props: &ImageFrameProps) -> Html {
+	// Where a drag started and the crop at the time. The photo follows the mouse until it's let go
+	let drag = use_state(|| None::<(i32, i32, ImageCrop)>);
+	let dragged_crop = use_state(|| None::<ImageCrop>);
+	let crop = dragged_crop.unwrap_or(props.crop);
+
+	let (frame_width, frame_height) = (props.width as f64, props.height as f64);
+	let image = props.file.as_ref()
+		.filter(|file| props.status == AssetStatus::Ready && file.width > 0 && file.height > 0)
+		.map(|file| (file, crop.layout(frame_width, frame_height, file.width as f64, file.height as f64)));
+	let can_pan = props.editable && image.is_some();
+
+	let frame_node_ref = use_node_ref();
+	use_selection_focus(frame_node_ref.clone(), props.selected && props.editable);
+
+	let onfocus = {
+		let onselect = props.onselect.clone();
+		let editable = props.editable;
+		move |_: FocusEvent| {
+			if editable {
+				onselect.emit(());
+			}
+		}
+	};
+
+	let onkeydown = {
+		let oncropchange = props.oncropchange.clone();
+		let onedit = props.onedit.clone();
+		let editable = props.editable;
+		let file = image.map(|(file, _)| file.clone());
+		move |event: KeyboardEvent| {
+			if !editable {
+				return;
+			}
+
+			let step = if event.shift_key() { LARGE_PAN_STEP } else { PAN_STEP };
+			let new_crop = match (event.key().as_str(), file.as_ref()) {
+				("Enter", _) => {
+					event.prevent_default();
+					onedit.emit(());
+					return;
+				}
+				("ArrowLeft", Some(file)) => pan(crop, file, frame_width, frame_height, -step, 0),
+				("ArrowRight", Some(file)) => pan(crop, file, frame_width, frame_height, step, 0),
+				("ArrowUp", Some(file)) => pan(crop, file, frame_width, frame_height, 0, -step),
+				("ArrowDown", Some(file)) => pan(crop, file, frame_width, frame_height, 0, step),
+				("+" | "=", Some(_)) => ImageCrop { zoom: (crop.zoom * ZOOM_STEP).clamp(1.0, MAX_ZOOM), ..crop },
+				("-", Some(_)) => ImageCrop { zoom: (crop.zoom / ZOOM_STEP).clamp(1.0, MAX_ZOOM), ..crop },
+				_ => return,
+			};
+			// Otherwise the page scrolls
+			event.prevent_default();
+			oncropchange.emit(new_crop);
+		}
+	};
+
+	let onmousedown = {
+		let drag = drag.clone();
+		let onselect = props.onselect.clone();
+		move |event: MouseEvent| {
+			if event.button() != 0 {
+				return;
+			}
+			onselect.emit(());
+			if can_pan {
+				// Stops the browser dragging the page's selection around instead
+				event.prevent_default();
+				drag.set(Some((event.client_x(), event.client_y(), crop)));
+			}
+		}
+	};
+
+	let onmousemove = {
+		let drag = drag.clone();
+		let dragged_crop = dragged_crop.clone();
+		let file = image.map(|(file, _)| file.clone());
+		move |event: MouseEvent| {
+			let (Some((start_x, start_y, start_crop)), Some(file)) = (*drag, file.as_ref()) else {
+				return;
+			};
+			dragged_crop.set(Some(pan(start_crop, file, frame_width, frame_height, event.client_x() - start_x, event.client_y() - start_y)));
+		}
+	};
+
+	// Dragging stops when the mouse is let go or leaves the frame
+	let end_drag = {
+		let drag = drag.clone();
+		let dragged_crop = dragged_crop.clone();
+		let oncropchange = props.oncropchange.clone();
+		move |_: MouseEvent| {
+			if drag.is_some() {
+				drag.set(None);
+				if let Some(crop) = *dragged_crop {
+					oncropchange.emit(crop);
+				}
+				dragged_crop.set(None);
+			}
+		}
+	};
+
+	let onwheel = {
+		let oncropchange = props.oncropchange.clone();
+		move |event: WheelEvent| {
+			if !can_pan || event.delta_y() == 0.0 {
+				return;
+			}
+			// Zoom the photo rather than scroll the page
+			event.prevent_default();
+			let step = if event.delta_y() < 0.0 { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+			oncropchange.emit(ImageCrop { zoom: (crop.zoom * step).clamp(1.0, MAX_ZOOM), ..crop });
+		}
+	};
+
+	let editable = props.editable;
+	// Files can only be dropped where the drag over is cancelled
+	let ondragover = move |event: DragEvent| {
+		if editable {
+			event.prevent_default();
+		}
+	};
+
+	let ondrop = {
+		let ondropfiles = props.ondropfiles.clone();
+		move |event: DragEvent| {
+			if !editable {
+				return;
+			}
+			// Otherwise the browser opens the file, and the canvas adds it as well
+			event.prevent_default();
+			event.stop_propagation();
+			if let Some(files) = event.data_transfer().and_then(|data| data.files()) {
+				ondropfiles.emit(files);
+			}
+		}
+	};
+
+	let background = match image {
+		Some((file, (x, y, width, height))) => format!(
+			"background-image: url({}); background-size: {}px {}px; background-position: {}px {}px; background-repeat: no-repeat;",
+			image_cache::object_url(props.item_id, file), width, height, x, y,
+		),
+		None => String::new(),
+	};
+	let style = format!(
+		"position: absolute; left: {}px; top: {}px; width: {}px; height: {}px; overflow: hidden; {}{}",
+		props.x, props.y, props.width, props.height, background,
+		if can_pan { "cursor: move;" } else { "" },
+	);
+
+	let class = classes!(
+		props.class.to_string(),
+		image.is_none().then_some("empty-placeholder"),
+		match props.status {
+			AssetStatus::Ready => None,
+			AssetStatus::Loading => Some("image-loading"),
+			AssetStatus::Failed(_) => Some("image-failed"),
+		},
+	);
+	let title = match &props.status {
+		AssetStatus::Failed(err) => Some(AttrValue::from(err.clone())),
+		_ => None,
+	};
+
+	html! {
+		<div ref={frame_node_ref} id={props.id.clone()} {class} {style} {title} tabindex={props.editable.then_some("0")} {onfocus} {onkeydown} {onmousedown} {onmousemove} onmouseup={end_drag.clone()} onmouseleave={end_drag} {onwheel} {ondragover} {ondrop}>
+			if image.is_none() {
+				<span class="placeholder-text" data-html2canvas-ignore="true">{ props.text.clone() }</span>
+			}
+			{ props.children.clone() }
+		</div>
+	}
+}
+
+// Move the photo by a number of pixels. The change in position is the distance moved over how far the
+// photo can move. It can't move along sides where it only just covers the frame
+fn pan(crop: ImageCrop, file: &FileDetails, frame_width: f64, frame_height: f64, moved_x: i32, moved_y: i32) -> ImageCrop {
+	let (_, _, width, height) = crop.layout(frame_width, frame_height, file.width as f64, file.height as f64);
+	let pan = |position: f64, moved: i32, slack: f64| {
+		if slack < 0.0 { (position + moved as f64 / slack).clamp(0.0, 1.0) } else { position }
+	};
+	ImageCrop {
+		position_x: pan(crop.position_x, moved_x, frame_width - width),
+		position_y: pan(crop.position_y, moved_y, frame_height - height),
+		..crop
+	}
+}
@@ -1,12 +1,15 @@
 #[path = "generic_movable_div.rs"]
 mod generic_movable_div;
 
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
 use yew::prelude::*;
 use crate::file_details::FileDetails;
+use crate::image_cache;
+use super::assets::AssetStatus;
 use generic_movable_div::MouseMoveComponent;
 
+// Size of the placeholder for an image that hasn't loaded, if it wasn't given one
+static PLACEHOLDER_SIZE: i32 = 250;
+
 #[derive(PartialEq, Properties)]
 pub struct MovableImageProps {
 	#[prop_or_default]
@@ -25,74 +28,78 @@ pub struct MovableImageProps {
 	pub start_x: Option<i32>,
 	#[prop_or(None)]
 	pub start_y: Option<i32>,
+	#[prop_or_default]
+	pub ondelete: Callback<()>,
+	#[prop_or_default]
+	pub onchange: Callback<(i32, i32, i32, i32)>,
+	#[prop_or_default]
+	pub onselect: Callback<()>,
+	#[prop_or(false)]
+	pub selected: bool,
+	pub item_id: u32,
 	pub file: FileDetails,
+	#[prop_or(AssetStatus::Ready)]
+	pub status: AssetStatus,
+	// Shown in place of the image while it's loading or if it failed to load
+	#[prop_or_default]
+	pub status_text: AttrValue,
 }
 
 #[function_component]
 pub fn MovableImageComponent(props: &MovableImageProps) -> Html {
 	let id = props.id.clone();
-	let class = props.class.clone();
+	let class = match &props.status {
+		AssetStatus::Ready => props.class.clone(),
+		AssetStatus::Loading => format!("{} image-loading", props.class).into(),
+		AssetStatus::Failed(_) => format!("{} image-failed", props.class).into(),
+	};
 	let extra_style = props.style.clone();
-	let file = props.file.clone();
+	let mut file = props.file.clone();
 	let start_x = props.start_x.clone();
 	let start_y = props.start_y.clone();
+	let ondelete = props.ondelete.clone();
+	let onchange = props.onchange.clone();
+	let onselect = props.onselect.clone();
 
-	let first_load = use_state(|| true);
-
-	let mut width = None;
-	let mut height = None;
-
-	// Calculate size of the div based on what was passed in and the image dimensions
-	if *first_load {
-		// Need these because we can't check the new value of width and height. They'll always read
-		// as 0
-		let mut tmp_width: i32 = 0;
-		let mut tmp_height: i32 = 0;
-		// Calculate size of the div based on what was passed in
-		if let Some(passed_width) = props.width {
-			tmp_width = passed_width;
-		}
-
-		if let Some(passed_height) = props.height {
-			tmp_height = passed_height;
-		}
-
+	// Images that haven't loaded don't have a size yet, so their placeholder is square
+	if file.width <= 0 || file.height <= 0 {
+		file.width = PLACEHOLDER_SIZE;
+		file.height = PLACEHOLDER_SIZE;
+	}
 
-		// Now if the width and height are 0, set them to the actual image size
-		if tmp_width == 0 && tmp_height == 0 {
-			tmp_width = file.width;
-			tmp_height = file.height;
-		} else if tmp_width == 0 {
-			// We know tmp_height is not 0 because if it was the above if statement would have been true
-			// Set width to maintain aspect ratio
-			let aspect_ratio = file.width as f32 / file.height as f32;
-			let new_width = (tmp_height as f32 * aspect_ratio) as i32;
-			tmp_width = new_width;
-		} else if tmp_height == 0 {
-			// Set height to maintain aspect ratio
-			let aspect_ratio = file.width as f32 / file.height as f32;
-			let new_height = (tmp_width as f32 * aspect_ratio) as i32;
-			tmp_height = new_height;
+	// Calculate size of the div based on what was passed in and the image dimensions. This is done on
+	// every render, so changes to the item show up. A missing dimension keeps the image's aspect ratio,
+	// the same as print_quality::item_area, and with neither the image is its own size
+	let (width, height) = {
+		let passed_width = props.width.filter(|width| *width > 0).map(f64::from);
+		let passed_height = props.height.filter(|height| *height > 0).map(f64::from);
+		match file.scaled_size(passed_width, passed_height) {
+			Some((width, height)) => (Some(width.round() as i32), Some(height.round() as i32)),
+			None => (Some(file.width), Some(file.height)),
 		}
+	};
 
-		width = Some(tmp_width);
-		height = Some(tmp_height);
-
-		first_load.set(false);
-	}
-
-	let style = format!(
-		"background: url({}); background-position: center; background-size: 100% 100%; background-repeat: no-repeat; {}",
-		format!(
-			"data:{};base64,{}",
-			file.clone().file_type,
-			STANDARD.encode(&file.clone().data)
+	let (style, status) = match &props.status {
+		AssetStatus::Ready => (
+			format!(
+				"background: url({}); background-position: center; background-size: 100% 100%; background-repeat: no-repeat; {}",
+				image_cache::object_url(props.item_id, &file),
+				extra_style,
+			),
+			html! {},
 		),
-		extra_style,
-	);
+		// There's nothing to make an object URL from until the image has loaded
+		AssetStatus::Loading => (extra_style.to_string(), html! {
+			<div class="image-status" data-html2canvas-ignore="true">{ props.status_text.clone() }</div>
+		}),
+		AssetStatus::Failed(err) => (extra_style.to_string(), html! {
+			<div class="image-status" data-html2canvas-ignore="true" title={err.clone()}>{ props.status_text.clone() }</div>
+		}),
+	};
 
 	html! {
-		<MouseMoveComponent {id} {class} {style} {width} {height} {start_x} {start_y}>
+		<MouseMoveComponent {id} {class} {style} {width} {height} {start_x} {start_y} {ondelete} {onchange} {onselect} selected={props.selected}>
+			{ status }
 			{ props.children.clone() }
 		</MouseMoveComponent>
 	}
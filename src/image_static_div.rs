@@ -1,7 +1,7 @@
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
 use yew::prelude::*;
 use crate::file_details::FileDetails;
+use crate::image_cache;
+use super::assets::AssetStatus;
 
 #[derive(PartialEq, Properties)]
 pub struct StaticImageProps {
@@ -21,7 +21,10 @@ pub struct StaticImageProps {
 	pub x: Option<i32>,
 	#[prop_or(None)]
 	pub y: Option<i32>,
+	pub item_id: u32,
 	pub file: FileDetails,
+	#[prop_or(AssetStatus::Ready)]
+	pub status: AssetStatus,
 }
 
 #[function_component(Image)]
@@ -33,57 +36,26 @@ pub fn image_component(props: &StaticImageProps) -> Html {
 	let left = props.x.clone();
 	let top = props.y.clone();
 
-	let first_load = use_state(|| true);
-
-	let mut width = None;
-	let mut height = None;
-
-	// Calculate size of the div based on what was passed in and the image dimensions
-	if *first_load {
-		// Need these because we can't check the new value of width and height. They'll always read
-		// as 0
-		let mut tmp_width: i32 = 0;
-		let mut tmp_height: i32 = 0;
-		// Calculate size of the div based on what was passed in
-		if let Some(passed_width) = props.width {
-			tmp_width = passed_width;
-		}
-
-		if let Some(passed_height) = props.height {
-			tmp_height = passed_height;
-		}
-
-
-		// Now if the width and height are 0, set them to the actual image size
-		if tmp_width == 0 && tmp_height == 0 {
-			tmp_width = file.width;
-			tmp_height = file.height;
-		} else if tmp_width == 0 {
-			// We know tmp_height is not 0 because if it was the above if statement would have been true
-			// Set width to maintain aspect ratio
-			let aspect_ratio = file.width as f32 / file.height as f32;
-			let new_width = (tmp_height as f32 * aspect_ratio) as i32;
-			tmp_width = new_width;
-		} else if tmp_height == 0 {
-			// Set height to maintain aspect ratio
-			let aspect_ratio = file.width as f32 / file.height as f32;
-			let new_height = (tmp_width as f32 * aspect_ratio) as i32;
-			tmp_height = new_height;
+	// Calculate size of the div based on what was passed in and the image dimensions. This is done on
+	// every render, so changes to the item show up. A missing dimension keeps the image's aspect ratio,
+	// the same as print_quality::item_area, and with neither the image is its own size
+	let (width, height) = {
+		let passed_width = props.width.filter(|width| *width > 0).map(f64::from);
+		let passed_height = props.height.filter(|height| *height > 0).map(f64::from);
+		match file.scaled_size(passed_width, passed_height) {
+			Some((width, height)) => (Some(width.round() as i32), Some(height.round() as i32)),
+			None => (Some(file.width), Some(file.height)),
 		}
+	};
 
-		width = Some(tmp_width);
-		height = Some(tmp_height);
-
-		first_load.set(false);
-	}
-
+	// Images that haven't loaded are left blank
+	let background = match props.status {
+		AssetStatus::Ready => format!("background: url({}); ", image_cache::object_url(props.item_id, &file)),
+		_ => String::new(),
+	};
 	let style = format!(
-		"background: url({}); background-position: center; background-size: 100% 100%; background-repeat: no-repeat; width: {}px; height: {}px; top: {}px; left: {}px; {}",
-		format!(
-			"data:{};base64,{}",
-			file.clone().file_type,
-			STANDARD.encode(&file.clone().data)
-		),
+		"{}background-position: center; background-size: 100% 100%; background-repeat: no-repeat; width: {}px; height: {}px; top: {}px; left: {}px; {}",
+		background,
 		width.unwrap_or(250),
 		height.unwrap_or(250),
 		top.unwrap_or(0),
@@ -7,7 +7,7 @@ use crate::console;
 
 // Function to construct a PageItems struct from javascript with default values for values not provided
 #[wasm_bindgen(js_name = build_item)]
-pub fn js_build_item(text_js: JsValue, file_js: JsValue, x: Option<i32>, y: Option<i32>, width: Option<i32>, height: Option<i32>, movable: Option<bool>) -> Result<JsValue, JsValue> {
+pub fn js_build_item(text_js: JsValue, file_js: JsValue, x: Option<f64>, y: Option<f64>, width: Option<f64>, height: Option<f64>, movable: Option<bool>) -> Result<JsValue, JsValue> {
 	// Check if text is undefined	
 	let text: Option<TextDetails> = match text_js.is_undefined() {
 		true => None,
@@ -36,8 +36,8 @@ pub fn js_build_item(text_js: JsValue, file_js: JsValue, x: Option<i32>, y: Opti
 	let item = PageItemsBuilder::default()
 		.text(text)
 		.file(file)
-		.x(x.unwrap_or(0))
-		.y(y.unwrap_or(0))
+		.x(x.unwrap_or(0.0))
+		.y(y.unwrap_or(0.0))
 		.width(width)
 		.height(height)
 		.movable(movable.unwrap_or(true))
@@ -81,6 +81,7 @@ pub fn js_build_file(name: String, file_type: String, data: Vec<u8>) -> Result<J
 		data,
 		width,
 		height,
+		source: None,
 	};
 	Ok(serde_wasm_bindgen::to_value(&file).map_err(|_| "Failed to serialize file")?)
 }
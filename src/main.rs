@@ -2,22 +2,101 @@ mod app;
 pub mod file_details;
 pub mod text_details;
 pub mod page_items;
+pub mod mockup;
+mod image_cache;
+mod js_funcs;
+#[cfg(not(any(feature = "standalone", target_arch = "wasm32")))]
+mod cli;
 
+#[cfg(not(feature = "standalone"))]
+use std::sync::atomic::{AtomicU32, Ordering};
 #[cfg(not(feature = "standalone"))]
 use wasm_bindgen::prelude::*;
-use web_sys::console;
-use app::App;
+use web_sys::{console, Element};
+use app::{App, AppProps};
+use app::editor_handle::EditorHandle;
+use app::editor_events::EditorEvents;
+use app::assets::AssetLoader;
+use app::iframe_messages;
+use app::config::EditorConfig;
+
+// Number of editors started with create_editor, for their default id prefixes
+#[cfg(not(feature = "standalone"))]
+static EDITOR_COUNT: AtomicU32 = AtomicU32::new(0);
 
 fn main() {
 	#[cfg(feature = "standalone")]
-	yew::Renderer::<App>::new().render();
+	{
+		let handle = mount(None, &EditorConfig::for_page());
+		handle.set_default();
+		// Only a parent page on the same origin can send messages
+		iframe_messages::listen(&handle, Vec::new());
+	}
+
+	// Native builds are command line tools
+	#[cfg(not(any(feature = "standalone", target_arch = "wasm32")))]
+	cli::run();
+}
+
+// Start an editor in root, or the body if there isn't one
+fn mount(root: Option<Element>, config: &EditorConfig) -> EditorHandle {
+	// Events are dispatched on the element the editor is in, so the host page can tell editors apart
+	let target = root.clone().or_else(|| web_sys::window()?.document()?.body().map(Element::from));
+	let events = EditorEvents::new(target);
+	let assets = AssetLoader::default();
+	let props = AppProps {
+		events: events.clone(),
+		assets: assets.clone(),
+		config: config.clone(),
+	};
+	let renderer = match root {
+		Some(root) => yew::Renderer::<App>::with_root_and_props(root, props),
+		None => yew::Renderer::<App>::with_props(props),
+	};
+	EditorHandle::new((*renderer.render()).clone(), events, assets)
 }
 
+// Start the editor in the #canvas-root element. Returns a handle to control it with, which the
+// js/ helper functions use as well
+// allowed_origins are the origins a parent page can control the editor from when it's in an
+// iframe, e.g. ["https://shop.example.com"]. Pages on the editor's own origin always can
 #[cfg(not(feature = "standalone"))]
-#[wasm_bindgen(start)]
-pub fn run_app() {
+#[wasm_bindgen]
+pub fn run_app(allowed_origins: Option<Vec<String>>) -> EditorHandle {
 	let document = web_sys::window().unwrap().document().unwrap();
 	let root = document.get_element_by_id("canvas-root").unwrap();
 	console::log_1(&"Starting app".into());
-	yew::Renderer::<App>::with_root(root).render();
+	let handle = mount(Some(root), &EditorConfig::for_page());
+	handle.set_default();
+	iframe_messages::listen(&handle, allowed_origins.unwrap_or_default());
+	handle
+}
+
+// Start an editor in element. There can be as many of these on a page as needed, each controlled
+// with the handle it returns. The first one started is also the one the js/ helper functions use
+// config is an EditorConfig, with the canvas, tools, fonts, starting design and so on. Without an
+// id_prefix the editor's element ids start with "editor-<n>-". The query string is ignored unless
+// query_override is true
+#[cfg(not(feature = "standalone"))]
+#[wasm_bindgen]
+pub fn create_editor(element: Element, config: JsValue) -> Result<EditorHandle, JsValue> {
+	let mut config: EditorConfig = if config.is_undefined() || config.is_null() {
+		EditorConfig::default()
+	} else {
+		serde_wasm_bindgen::from_value(config).map_err(|err| format!("Invalid config: {}", err))?
+	};
+	config.validate().map_err(|err| format!("Invalid config: {}", err))?;
+
+	if config.id_prefix.is_none() {
+		config.id_prefix = Some(format!("editor-{}-", EDITOR_COUNT.fetch_add(1, Ordering::Relaxed)));
+	}
+	let handle = mount(Some(element), &config);
+	if !EditorHandle::has_default() {
+		handle.set_default();
+	}
+	// Every editor listening would answer each message, so only ones that are asked to listen do
+	if let Some(allowed_origins) = config.allowed_origins {
+		iframe_messages::listen(&handle, allowed_origins);
+	}
+	Ok(handle)
 }
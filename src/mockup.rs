@@ -0,0 +1,91 @@
+// Product mockups the design can be previewed on
+use serde::{Deserialize, Serialize};
+
+// Where the design goes on the mockup photo, in pixels of the photo
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Placement {
+	Rect {
+		x: f64,
+		y: f64,
+		width: f64,
+		height: f64,
+	},
+	// Top left, top right, bottom right and bottom left corners. Used for designs seen at an angle
+	Quad {
+		corners: [[f64; 2]; 4],
+	},
+}
+
+impl Placement {
+	pub fn corners(&self) -> [[f64; 2]; 4] {
+		match self {
+			Placement::Rect { x, y, width, height } => [
+				[*x, *y],
+				[x + width, *y],
+				[x + width, y + height],
+				[*x, y + height],
+			],
+			Placement::Quad { corners } => *corners,
+		}
+	}
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct MockupTemplate {
+	pub name: String,
+	// URL of the product photo, and its size in pixels
+	pub image: String,
+	pub width: f64,
+	pub height: f64,
+	pub placement: Placement,
+	// Multiply the design with the photo, so folds and shadows show through it
+	#[serde(default)]
+	pub shading: bool,
+	// URL of an image used to warp the design to the shape of the product. The red and green
+	// channels move pixels horizontally and vertically
+	#[serde(default)]
+	pub displacement_map: Option<String>,
+	#[serde(default = "default_displacement_scale")]
+	pub displacement_scale: f64,
+}
+
+fn default_displacement_scale() -> f64 {
+	10.0
+}
+
+// Get a CSS transform that maps a width x height rectangle at the origin onto the given corners
+// Returns None if the corners can't be mapped to, e.g. if three of them are in a line
+pub fn quad_transform(width: f64, height: f64, corners: [[f64; 2]; 4]) -> Option<String> {
+	let [[x0, y0], [x1, y1], [x2, y2], [x3, y3]] = corners;
+
+	// Projective transform from the unit square to the corners
+	let dx1 = x1 - x2;
+	let dx2 = x3 - x2;
+	let dx3 = x0 - x1 + x2 - x3;
+	let dy1 = y1 - y2;
+	let dy2 = y3 - y2;
+	let dy3 = y0 - y1 + y2 - y3;
+
+	let det = dx1 * dy2 - dx2 * dy1;
+	if det == 0.0 || width == 0.0 || height == 0.0 {
+		return None;
+	}
+
+	let g = (dx3 * dy2 - dx2 * dy3) / det;
+	let h = (dx1 * dy3 - dx3 * dy1) / det;
+	let a = x1 - x0 + g * x1;
+	let b = x3 - x0 + h * x3;
+	let c = x0;
+	let d = y1 - y0 + g * y1;
+	let e = y3 - y0 + h * y3;
+	let f = y0;
+
+	// Scale the rectangle down to the unit square first. matrix3d is in column major order
+	Some(format!(
+		"matrix3d({}, {}, 0, {}, {}, {}, 0, {}, 0, 0, 1, 0, {}, {}, 0, 1)",
+		a / width, d / width, g / width,
+		b / height, e / height, h / height,
+		c, f,
+	))
+}
@@ -0,0 +1,59 @@
+// Shows the design on a photo of the product
+// The children are the design, laid out at the size of the canvas. They're transformed onto the
+// mockup's placement, and optionally shaded and warped to match the photo
+use yew::prelude::*;
+use crate::mockup::{MockupTemplate, quad_transform};
+
+#[derive(PartialEq, Properties)]
+pub struct MockupPreviewProps {
+	#[prop_or_default]
+	pub id: AttrValue,
+	#[prop_or_default]
+	pub children: Children,
+	pub mockup: MockupTemplate,
+	// Size of the design on screen in pixels. The preview is shown at the same width
+	pub design_width: i32,
+	pub design_height: i32,
+}
+
+#[function_component]
+pub fn MockupPreview(props: &MockupPreviewProps) -> Html {
+	let mockup = &props.mockup;
+	let filter_id = format!("{}-displacement", props.id);
+
+	// Scale the photo so it's as wide as the canvas
+	let scale = props.design_width as f64 / mockup.width;
+	let preview_height = mockup.height * scale;
+	let corners = mockup.placement.corners().map(|[x, y]| [x * scale, y * scale]);
+
+	let mut design_style = format!("position: absolute; left: 0; top: 0; width: {}px; height: {}px; overflow: hidden; transform-origin: 0 0;",
+		props.design_width,
+		props.design_height,
+	);
+	if let Some(transform) = quad_transform(props.design_width as f64, props.design_height as f64, corners) {
+		design_style.push_str(&format!(" transform: {};", transform));
+	}
+	if mockup.shading {
+		design_style.push_str(" mix-blend-mode: multiply;");
+	}
+	if mockup.displacement_map.is_some() {
+		design_style.push_str(&format!(" filter: url(#{});", filter_id));
+	}
+
+	html! {
+		<div id={props.id.clone()} class="mockup-preview" style={format!("position: relative; width: {}px; height: {}px; overflow: hidden;", props.design_width, preview_height)}>
+			<img src={mockup.image.clone()} alt={mockup.name.clone()} style="position: absolute; left: 0; top: 0; width: 100%; height: 100%;" />
+			if let Some(displacement_map) = mockup.displacement_map.clone() {
+				<svg width="0" height="0" style="position: absolute;">
+					<filter id={filter_id.clone()} x="0" y="0" width="100%" height="100%">
+						<feImage href={displacement_map} result="displacement" preserveAspectRatio="none" />
+						<feDisplacementMap in="SourceGraphic" in2="displacement" scale={mockup.displacement_scale.to_string()} xChannelSelector="R" yChannelSelector="G" />
+					</filter>
+				</svg>
+			}
+			<div class="mockup-design" style={design_style}>
+				{ props.children.clone() }
+			</div>
+		</div>
+	}
+}
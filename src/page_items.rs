@@ -1,22 +1,205 @@
+use std::sync::atomic::{AtomicU32, Ordering};
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
 use crate::text_details::TextDetails;
 use crate::file_details::FileDetails;
 
+static NEXT_ITEM_ID: AtomicU32 = AtomicU32::new(1);
+
+// Returns a new id that is unique for this page, used to tell items apart after they're created
+pub fn next_item_id() -> u32 {
+	NEXT_ITEM_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// What the customer can change about an item. Templates use these to only let some parts of a
+// design be customised
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemRole {
+	// Anything, as long as the item is movable
+	#[default]
+	Free,
+	// Nothing. It can't be moved, resized, deleted or edited
+	Locked,
+	// Only the text. It can't be moved, resized or deleted
+	TextOnly,
+	// A frame for the customer's photo, e.g. "drop your photo here". It can't be moved, resized or
+	// deleted, but the image can be replaced. Images are cropped to fill the frame
+	Placeholder,
+}
+
+// How a photo sits in the placeholder it fills. At zoom 1 it just covers the frame. position_x and
+// position_y are which part of it shows, from 0 (the left or top edge) to 1 (the right or bottom
+// edge), the same as CSS background-position percentages
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ImageCrop {
+	pub zoom: f64,
+	pub position_x: f64,
+	pub position_y: f64,
+}
+
+impl Default for ImageCrop {
+	fn default() -> Self {
+		Self {
+			zoom: 1.0,
+			position_x: 0.5,
+			position_y: 0.5,
+		}
+	}
+}
+
+impl ImageCrop {
+	// Where the image goes in a frame, as (x, y, width, height) from the frame's top left corner.
+	// Works in any units, as long as the frame and image sizes are in the same ones
+	pub fn layout(&self, frame_width: f64, frame_height: f64, image_width: f64, image_height: f64) -> (f64, f64, f64, f64) {
+		let scale = (frame_width / image_width).max(frame_height / image_height) * self.zoom.max(1.0);
+		let (width, height) = (image_width * scale, image_height * scale);
+		let x = (frame_width - width) * self.position_x.clamp(0.0, 1.0);
+		let y = (frame_height - height) * self.position_y.clamp(0.0, 1.0);
+		(x, y, width, height)
+	}
+}
+
 #[derive(PartialEq, Clone, Builder, Serialize, Deserialize)]
 pub struct PageItems {
+	#[builder(default = "next_item_id()")]
+	#[serde(default = "next_item_id")]
+	pub id: u32,
 	#[builder(default)]
 	pub text: Option<TextDetails>,
 	#[builder(default)]
 	pub file: Option<FileDetails>,
-	#[builder(default = "0")]
-	pub x: i32,
-	#[builder(default = "0")]
-	pub y: i32,
+	// Geometry is in the units of the canvas (see CanvasSettings)
+	#[builder(default = "0.0")]
+	pub x: f64,
+	#[builder(default = "0.0")]
+	pub y: f64,
 	#[builder(default)]
-	pub width: Option<i32>,
+	pub width: Option<f64>,
 	#[builder(default)]
-	pub height: Option<i32>,
+	pub height: Option<f64>,
 	#[builder(default = "true")]
 	pub movable: bool,
+	#[builder(default)]
+	#[serde(default)]
+	pub role: ItemRole,
+	// Only used by placeholders
+	#[builder(default)]
+	#[serde(default)]
+	pub crop: ImageCrop,
+}
+
+impl PageItems {
+	// Whether the customer can move, resize and delete the item
+	pub fn is_movable(&self) -> bool {
+		self.movable && self.role == ItemRole::Free
+	}
+
+	// Whether the customer can change the item in any way, so it can be selected with the keyboard
+	pub fn is_customizable(&self) -> bool {
+		self.is_movable() || matches!(self.role, ItemRole::TextOnly | ItemRole::Placeholder)
+	}
+
+	// Whether the image is cropped to fill the item, rather than stretched to it
+	pub fn fills_frame(&self) -> bool {
+		self.role == ItemRole::Placeholder
+	}
+
+	// A placeholder the customer hasn't put a photo in yet
+	pub fn is_empty_placeholder(&self) -> bool {
+		self.role == ItemRole::Placeholder && self.file.is_none()
+	}
+
+	// Check that the host page's changes only touch what the role lets the customer change. changed
+	// is the item with the changes made
+	pub fn check_changes(&self, changed: &PageItems) -> Result<(), String> {
+		let mut allowed = self.clone();
+		let message = match self.role {
+			ItemRole::Free => return Ok(()),
+			ItemRole::Locked => "is locked",
+			ItemRole::TextOnly => {
+				if let (Some(text), Some(changed_text)) = (allowed.text.as_mut(), changed.text.as_ref()) {
+					text.text = changed_text.text.clone();
+				}
+				"is text_only, so only its text can change"
+			}
+			ItemRole::Placeholder => {
+				allowed.file = changed.file.clone();
+				allowed.crop = changed.crop;
+				"is a placeholder, so only its photo and crop can change"
+			}
+		};
+
+		match *changed == allowed {
+			true => Ok(()),
+			false => Err(format!("Item {} {}. Use force to change it anyway", self.id, message)),
+		}
+	}
+
+	// Only free items can be removed, the same as in the editor
+	pub fn check_remove(&self) -> Result<(), String> {
+		match self.role {
+			ItemRole::Free => Ok(()),
+			_ => Err(format!("Item {} is part of the template, so it can't be removed. Use force to remove it anyway", self.id)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::text_details::TextDetailsBuilder;
+	use super::*;
+
+	fn template_item(role: ItemRole) -> PageItems {
+		let text = TextDetailsBuilder::default().text("Your name".to_string()).build().unwrap();
+		PageItemsBuilder::default().id(1).text(Some(text)).x(20.0).y(30.0).width(Some(100.0)).role(role).build().unwrap()
+	}
+
+	#[test]
+	fn free_items_can_change_anything() {
+		let item = template_item(ItemRole::Free);
+		let mut changed = item.clone();
+		changed.x = 50.0;
+		assert!(item.check_changes(&changed).is_ok());
+		assert!(item.check_remove().is_ok());
+	}
+
+	#[test]
+	fn locked_items_cant_change() {
+		let item = template_item(ItemRole::Locked);
+		assert!(item.check_changes(&item.clone()).is_ok());
+
+		let mut changed = item.clone();
+		changed.text.as_mut().unwrap().text = "Hello".to_string();
+		assert!(item.check_changes(&changed).is_err());
+		assert!(item.check_remove().is_err());
+	}
+
+	#[test]
+	fn text_only_items_can_only_change_text() {
+		let item = template_item(ItemRole::TextOnly);
+		let mut changed = item.clone();
+		changed.text.as_mut().unwrap().text = "Hello".to_string();
+		assert!(item.check_changes(&changed).is_ok());
+
+		changed.x = 50.0;
+		assert!(item.check_changes(&changed).is_err());
+
+		let mut changed = item.clone();
+		changed.text.as_mut().unwrap().font_size = 40;
+		assert!(item.check_changes(&changed).is_err());
+	}
+
+	#[test]
+	fn placeholders_can_only_change_crop() {
+		let item = template_item(ItemRole::Placeholder);
+		let mut changed = item.clone();
+		changed.crop.zoom = 2.0;
+		assert!(item.check_changes(&changed).is_ok());
+
+		changed.width = Some(200.0);
+		assert!(item.check_changes(&changed).is_err());
+		assert!(item.check_remove().is_err());
+	}
 }
@@ -0,0 +1,651 @@
+// Writes surfaces to a PDF for print shops
+// Nothing is rasterized: text is written as real text in an embedded font, images are embedded at
+// their original resolution, and the background and mask are vector paths. This doesn't use the
+// browser at all, so the CLI can use it too
+use std::collections::{BTreeMap, HashMap};
+use image::{ColorType, ImageFormat};
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+use svgtypes::{Color, SimplePathSegment, SimplifyingPathParser};
+use ttf_parser::{Face, GlyphId};
+use crate::page_items::PageItems;
+use crate::text_details::TextDetails;
+use super::font_subset::subset_font;
+use super::print_quality::item_area;
+use super::settings::{Area, CanvasSettings};
+
+// Files the PDF needs that aren't part of the design
+#[derive(Default)]
+pub struct PdfResources {
+	// TrueType/OpenType font files, keyed by font family. Text in any other family is written in
+	// one of the standard PDF fonts, which aren't embedded
+	pub fonts: HashMap<String, Vec<u8>>,
+	// Background images, keyed by the URL in the canvas settings
+	pub images: HashMap<String, Vec<u8>>,
+}
+
+// One page of the PDF
+pub struct PdfPage<'a> {
+	pub settings: &'a CanvasSettings,
+	pub items: &'a [PageItems],
+}
+
+// Write every page to a PDF. Each page is the size of its canvas, including the bleed
+pub fn export_pdf(pages: &[PdfPage], resources: &PdfResources) -> Result<Vec<u8>, String> {
+	let mut writer = PdfWriter::new(resources);
+	let catalog_id = writer.alloc();
+	let page_tree_id = writer.alloc();
+
+	let mut page_ids = Vec::new();
+	for page in pages {
+		page_ids.push(writer.write_page(page, page_tree_id)?);
+	}
+
+	writer.write_fonts();
+	writer.pdf.catalog(catalog_id).pages(page_tree_id);
+	writer.pdf.pages(page_tree_id).kids(page_ids.iter().copied()).count(page_ids.len() as i32);
+	Ok(writer.pdf.finish())
+}
+
+// A font used by the PDF
+enum PdfFont<'a> {
+	// Embedded font file, with every glyph used so far and the character it was used for
+	Embedded { id: Ref, name: String, face: Box<Face<'a>>, data: &'a [u8], glyphs: BTreeMap<u16, char> },
+	// One of the standard 14 fonts every PDF reader has
+	Standard { id: Ref, name: String, base_font: &'static str },
+}
+
+impl PdfFont<'_> {
+	fn name(&self) -> &str {
+		match self {
+			PdfFont::Embedded { name, .. } | PdfFont::Standard { name, .. } => name,
+		}
+	}
+
+	// Top of the em box to the baseline, as a fraction of the font size
+	fn ascent(&self) -> f32 {
+		match self {
+			PdfFont::Embedded { face, .. } => face.ascender() as f32 / face.units_per_em() as f32,
+			PdfFont::Standard { .. } => 0.8,
+		}
+	}
+
+	// Encode text for this font, keeping track of the glyphs it uses
+	fn encode(&mut self, text: &str) -> Vec<u8> {
+		match self {
+			// Identity-H encoding, so every character is its two byte glyph id
+			PdfFont::Embedded { face, glyphs, .. } => text.chars()
+				.flat_map(|c| {
+					let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+					glyphs.entry(glyph.0).or_insert(c);
+					glyph.0.to_be_bytes()
+				})
+				.collect(),
+			// WinAnsiEncoding matches Latin-1 for printable characters. Anything else can't be shown
+			PdfFont::Standard { .. } => text.chars()
+				.map(|c| match c as u32 {
+					0x20..=0x7e | 0xa0..=0xff => c as u8,
+					_ => b'?',
+				})
+				.collect(),
+		}
+	}
+}
+
+struct PdfWriter<'a> {
+	pdf: Pdf,
+	resources: &'a PdfResources,
+	next_id: i32,
+	// Keyed by font family
+	fonts: HashMap<String, PdfFont<'a>>,
+}
+
+impl<'a> PdfWriter<'a> {
+	fn new(resources: &'a PdfResources) -> Self {
+		Self {
+			pdf: Pdf::new(),
+			resources,
+			next_id: 1,
+			fonts: HashMap::new(),
+		}
+	}
+
+	fn alloc(&mut self) -> Ref {
+		let id = Ref::new(self.next_id);
+		self.next_id += 1;
+		id
+	}
+
+	fn write_page(&mut self, page: &PdfPage, page_tree_id: Ref) -> Result<Ref, String> {
+		let settings = page.settings;
+		let page_id = self.alloc();
+		let content_id = self.alloc();
+		let transform = PageTransform::new(settings);
+
+		let mut content = Content::new();
+		let mut images = Vec::new();
+		let mut fonts = Vec::new();
+
+		if let Some(path) = &settings.mask_path {
+			// The mask image needs a browser to draw it, so only mask paths are supported
+			write_path(&mut content, path, &transform)?;
+			content.clip_nonzero();
+			content.end_path();
+		}
+
+		if !settings.transparent_export {
+			if let Some(color) = &settings.background_color {
+				if set_fill_color(&mut content, color) {
+					content.rect(0.0, 0.0, transform.page_width, transform.page_height);
+					content.fill_nonzero();
+				}
+			}
+
+			if let Some(data) = settings.background_image.as_ref().and_then(|url| self.resources.images.get(url)) {
+				let (image_id, width, height) = self.write_image(data)?;
+				let name = format!("Im{}", images.len());
+
+				// Scale the image to cover the whole page, keeping it centered
+				let scale = (transform.page_width / width as f32).max(transform.page_height / height as f32);
+				let draw_width = width as f32 * scale;
+				let draw_height = height as f32 * scale;
+				content.save_state();
+				content.transform([
+					draw_width, 0.0, 0.0, draw_height,
+					(transform.page_width - draw_width) / 2.0,
+					(transform.page_height - draw_height) / 2.0,
+				]);
+				content.x_object(Name(name.as_bytes()));
+				content.restore_state();
+				images.push((name, image_id));
+			}
+		}
+
+		// The first item is drawn on top, same as on the page
+		for item in page.items.iter().rev() {
+			let area = transform.area(&item_area(item, settings));
+
+			if let Some(file) = &item.file {
+				let (image_id, width, height) = self.write_image(&file.data)
+					.map_err(|err| format!("Failed to embed image {}: {}", file.name, err))?;
+				let name = format!("Im{}", images.len());
+				content.save_state();
+				if item.fills_frame() {
+					// Place the image the way it's cropped on the page, and cut off what's outside the frame
+					content.rect(area.x as f32, area.y as f32, area.width as f32, area.height as f32);
+					content.clip_nonzero();
+					content.end_path();
+					let (x, y, draw_width, draw_height) = item.crop.layout(area.width, area.height, width as f64, height as f64);
+					// PDF y coordinates go up from the bottom of the page
+					content.transform([
+						draw_width as f32, 0.0, 0.0, draw_height as f32,
+						(area.x + x) as f32,
+						(area.y + area.height - y - draw_height) as f32,
+					]);
+				} else {
+					content.transform([area.width as f32, 0.0, 0.0, area.height as f32, area.x as f32, area.y as f32]);
+				}
+				content.x_object(Name(name.as_bytes()));
+				content.restore_state();
+				images.push((name, image_id));
+			} else if let Some(text) = &item.text {
+				let font_size = transform.scale * settings.from_screen(text.font_size as i32) as f32;
+				let font_name = self.write_text(&mut content, text, &area, font_size);
+				if !fonts.contains(&font_name) {
+					fonts.push(font_name);
+				}
+			}
+		}
+
+		let content = content.finish();
+		self.pdf.stream(content_id, &compress_to_vec_zlib(&content, 6)).filter(Filter::FlateDecode);
+
+		let media_box = if settings.export_bleed {
+			Rect::new(0.0, 0.0, transform.page_width, transform.page_height)
+		} else {
+			transform.rect(&settings.trim_area())
+		};
+
+		let mut pdf_page = self.pdf.page(page_id);
+		pdf_page.parent(page_tree_id)
+			.media_box(media_box)
+			.bleed_box(media_box)
+			.trim_box(transform.rect(&settings.trim_area()))
+			.contents(content_id);
+
+		let mut page_resources = pdf_page.resources();
+		let mut x_objects = page_resources.x_objects();
+		for (name, id) in &images {
+			x_objects.pair(Name(name.as_bytes()), *id);
+		}
+		x_objects.finish();
+
+		let mut font_resources = page_resources.fonts();
+		for family in &fonts {
+			let font = &self.fonts[family];
+			let id = match font {
+				PdfFont::Embedded { id, .. } | PdfFont::Standard { id, .. } => *id,
+			};
+			font_resources.pair(Name(font.name().as_bytes()), id);
+		}
+		font_resources.finish();
+		page_resources.finish();
+		pdf_page.finish();
+
+		Ok(page_id)
+	}
+
+	// Draw text the same way the text components show it: a filled box with the text in the top
+	// left. Returns the font family used
+	fn write_text(&mut self, content: &mut Content, text: &TextDetails, area: &Area, font_size: f32) -> String {
+		let font = self.font(&text.font_family);
+		let ascent = font.ascent();
+		let font_name = font.name().to_string();
+
+		content.save_state();
+		content.rect(area.x as f32, area.y as f32, area.width as f32, area.height as f32);
+		content.clip_nonzero();
+		content.end_path();
+
+		if set_fill_color(content, &text.background_color) {
+			content.rect(area.x as f32, area.y as f32, area.width as f32, area.height as f32);
+			content.fill_nonzero();
+		}
+
+		set_fill_color(content, &text.font_color);
+		content.begin_text();
+		content.set_font(Name(font_name.as_bytes()), font_size);
+		let top = (area.y + area.height) as f32;
+		for (i, line) in text.text.lines().enumerate() {
+			let baseline = top - font_size * ascent - i as f32 * font_size * 1.2;
+			content.set_text_matrix([1.0, 0.0, 0.0, 1.0, area.x as f32, baseline]);
+			content.show(Str(&font.encode(line)));
+		}
+		content.end_text();
+		content.restore_state();
+
+		text.font_family.clone()
+	}
+
+	// Get the font for a family, setting it up the first time it's used
+	fn font(&mut self, family: &str) -> &mut PdfFont<'a> {
+		if !self.fonts.contains_key(family) {
+			let id = self.alloc();
+			let name = format!("F{}", self.fonts.len());
+			let face = self.resources.fonts.get(family)
+				.and_then(|data| Face::parse(data, 0).ok().map(|face| (Box::new(face), data.as_slice())));
+
+			let font = match face {
+				Some((face, data)) => PdfFont::Embedded { id, name, face, data, glyphs: BTreeMap::new() },
+				None => PdfFont::Standard { id, name, base_font: standard_font(family) },
+			};
+			self.fonts.insert(family.to_string(), font);
+		}
+
+		self.fonts.get_mut(family).unwrap()
+	}
+
+	// Fonts are written last, since embedded fonts need to know every glyph that was used
+	fn write_fonts(&mut self) {
+		let fonts: Vec<PdfFont> = self.fonts.drain().map(|(_, font)| font).collect();
+
+		for font in fonts {
+			match font {
+				PdfFont::Standard { id, base_font, .. } => {
+					self.pdf.type1_font(id)
+						.base_font(Name(base_font.as_bytes()))
+						.encoding_predefined(Name(b"WinAnsiEncoding"));
+				}
+				PdfFont::Embedded { id, face, data, glyphs, .. } => {
+					let cid_font_id = self.alloc();
+					let descriptor_id = self.alloc();
+					let font_file_id = self.alloc();
+					let to_unicode_id = self.alloc();
+
+					// Only the glyphs the text uses are embedded. PDF readers expect subset fonts to have a tag
+					// in front of their name
+					let subset = subset_font(data, glyphs.keys().copied());
+					let data = subset.as_deref().unwrap_or(data);
+					let base_font = match &subset {
+						Some(_) => format!("{}+{}", subset_tag(&glyphs), postscript_name(&face)),
+						None => postscript_name(&face),
+					};
+					let units = face.units_per_em() as f32;
+					let to_pdf = |value: f32| value * 1000.0 / units;
+					let system_info = SystemInfo {
+						registry: Str(b"Adobe"),
+						ordering: Str(b"Identity"),
+						supplement: 0,
+					};
+
+					self.pdf.type0_font(id)
+						.base_font(Name(base_font.as_bytes()))
+						.encoding_predefined(Name(b"Identity-H"))
+						.descendant_font(cid_font_id)
+						.to_unicode(to_unicode_id);
+
+					let mut cid_font = self.pdf.cid_font(cid_font_id);
+					cid_font.subtype(CidFontType::Type2)
+						.base_font(Name(base_font.as_bytes()))
+						.system_info(system_info)
+						.font_descriptor(descriptor_id)
+						.cid_to_gid_map_predefined(Name(b"Identity"));
+					let mut widths = cid_font.widths();
+					for glyph in glyphs.keys() {
+						let advance = face.glyph_hor_advance(GlyphId(*glyph)).unwrap_or(0);
+						widths.consecutive(*glyph, [to_pdf(advance as f32)]);
+					}
+					widths.finish();
+					cid_font.finish();
+
+					let bbox = face.global_bounding_box();
+					let mut flags = FontFlags::SYMBOLIC;
+					if face.is_italic() {
+						flags |= FontFlags::ITALIC;
+					}
+					self.pdf.font_descriptor(descriptor_id)
+						.name(Name(base_font.as_bytes()))
+						.flags(flags)
+						.bbox(Rect::new(
+							to_pdf(bbox.x_min as f32),
+							to_pdf(bbox.y_min as f32),
+							to_pdf(bbox.x_max as f32),
+							to_pdf(bbox.y_max as f32),
+						))
+						.italic_angle(face.italic_angle())
+						.ascent(to_pdf(face.ascender() as f32))
+						.descent(to_pdf(face.descender() as f32))
+						.cap_height(to_pdf(face.capital_height().unwrap_or(face.ascender()) as f32))
+						.stem_v(80.0)
+						.font_file2(font_file_id);
+
+					self.pdf.stream(font_file_id, &compress_to_vec_zlib(data, 6))
+						.filter(Filter::FlateDecode)
+						.pair(Name(b"Length1"), data.len() as i32);
+
+					// Lets PDF readers copy and search the text
+					let mut cmap = UnicodeCmap::new(Name(b"Custom"), system_info);
+					for (glyph, c) in glyphs {
+						cmap.pair(glyph, c);
+					}
+					self.pdf.cmap(to_unicode_id, &cmap.finish());
+				}
+			}
+		}
+	}
+
+	// Embed an image at its original resolution. Returns its id and size in pixels
+	fn write_image(&mut self, data: &[u8]) -> Result<(Ref, u32, u32), String> {
+		let image = image::load_from_memory(data).map_err(|err| err.to_string())?;
+		let (width, height) = (image.width(), image.height());
+		let image_id = self.alloc();
+
+		// JPEGs can be embedded as they are
+		let is_jpeg = image::guess_format(data).ok() == Some(ImageFormat::Jpeg);
+		if is_jpeg && matches!(image.color(), ColorType::L8 | ColorType::Rgb8) {
+			let mut xobject = self.pdf.image_xobject(image_id, data);
+			xobject.width(width as i32).height(height as i32).bits_per_component(8).filter(Filter::DctDecode);
+			if image.color() == ColorType::L8 {
+				xobject.color_space().device_gray();
+			} else {
+				xobject.color_space().device_rgb();
+			}
+			return Ok((image_id, width, height));
+		}
+
+		let rgb = image.to_rgb8();
+		let rgba = image.to_rgba8();
+		let has_alpha = rgba.pixels().any(|pixel| pixel.0[3] < 255);
+
+		let mask_id = if has_alpha {
+			let mask_id = self.alloc();
+			let alpha: Vec<u8> = rgba.pixels().map(|pixel| pixel.0[3]).collect();
+			let alpha = compress_to_vec_zlib(&alpha, 6);
+			let mut mask = self.pdf.image_xobject(mask_id, &alpha);
+			mask.width(width as i32).height(height as i32).bits_per_component(8).filter(Filter::FlateDecode);
+			mask.color_space().device_gray();
+			Some(mask_id)
+		} else {
+			None
+		};
+
+		let samples = compress_to_vec_zlib(rgb.as_raw(), 6);
+		let mut xobject = self.pdf.image_xobject(image_id, &samples);
+		xobject.width(width as i32).height(height as i32).bits_per_component(8).filter(Filter::FlateDecode);
+		xobject.color_space().device_rgb();
+		if let Some(mask_id) = mask_id {
+			xobject.s_mask(mask_id);
+		}
+
+		Ok((image_id, width, height))
+	}
+}
+
+// Converts canvas units to PDF points. PDFs have their origin in the bottom left, so y is flipped
+struct PageTransform {
+	// Points per canvas unit
+	scale: f32,
+	page_width: f32,
+	page_height: f32,
+}
+
+impl PageTransform {
+	fn new(settings: &CanvasSettings) -> Self {
+		// Pixel canvases are printed at the target DPI
+		let inches_per_unit = settings.to_inches(1.0).unwrap_or(1.0 / settings.dpi);
+		let scale = (inches_per_unit * 72.0) as f32;
+
+		Self {
+			scale,
+			page_width: settings.full_width_units() as f32 * scale,
+			page_height: settings.full_height_units() as f32 * scale,
+		}
+	}
+
+	fn point(&self, x: f64, y: f64) -> (f32, f32) {
+		(x as f32 * self.scale, self.page_height - y as f32 * self.scale)
+	}
+
+	// Area in points, with (x, y) as the bottom left corner
+	fn area(&self, area: &Area) -> Area {
+		Area {
+			x: area.x * self.scale as f64,
+			y: self.page_height as f64 - (area.y + area.height) * self.scale as f64,
+			width: area.width * self.scale as f64,
+			height: area.height * self.scale as f64,
+		}
+	}
+
+	fn rect(&self, area: &Area) -> Rect {
+		let area = self.area(area);
+		Rect::new(area.x as f32, area.y as f32, (area.x + area.width) as f32, (area.y + area.height) as f32)
+	}
+}
+
+// Add an SVG path in canvas units to the content
+fn write_path(content: &mut Content, path: &str, transform: &PageTransform) -> Result<(), String> {
+	// Quadratic curves need to know where the last segment ended
+	let mut start = (0.0, 0.0);
+	let mut current = (0.0, 0.0);
+
+	for segment in SimplifyingPathParser::from(path) {
+		match segment.map_err(|err| format!("Invalid mask path: {}", err))? {
+			SimplePathSegment::MoveTo { x, y } => {
+				let (x, y) = transform.point(x, y);
+				content.move_to(x, y);
+				start = (x, y);
+				current = (x, y);
+			}
+			SimplePathSegment::LineTo { x, y } => {
+				let (x, y) = transform.point(x, y);
+				content.line_to(x, y);
+				current = (x, y);
+			}
+			SimplePathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+				let (x1, y1) = transform.point(x1, y1);
+				let (x2, y2) = transform.point(x2, y2);
+				let (x, y) = transform.point(x, y);
+				content.cubic_to(x1, y1, x2, y2, x, y);
+				current = (x, y);
+			}
+			// PDFs only have cubic curves
+			SimplePathSegment::Quadratic { x1, y1, x, y } => {
+				let (x1, y1) = transform.point(x1, y1);
+				let (x, y) = transform.point(x, y);
+				let (x0, y0) = current;
+				content.cubic_to(
+					x0 + 2.0 / 3.0 * (x1 - x0), y0 + 2.0 / 3.0 * (y1 - y0),
+					x + 2.0 / 3.0 * (x1 - x), y + 2.0 / 3.0 * (y1 - y),
+					x, y,
+				);
+				current = (x, y);
+			}
+			SimplePathSegment::ClosePath => {
+				content.close_path();
+				current = start;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+// Set the fill color from a CSS color. Colors that can't be parsed are black
+// Returns false if the color is transparent, so there's nothing to fill
+fn set_fill_color(content: &mut Content, color: &str) -> bool {
+	let color = color.parse::<Color>().unwrap_or(Color::black());
+	content.set_fill_rgb(color.red as f32 / 255.0, color.green as f32 / 255.0, color.blue as f32 / 255.0);
+	color.alpha > 0
+}
+
+// Pick the standard font closest to a font family
+fn standard_font(family: &str) -> &'static str {
+	let family = family.to_lowercase();
+	if family.contains("courier") || family.contains("mono") {
+		"Courier"
+	} else if family.contains("times") || family.contains("georgia") || (family.contains("serif") && !family.contains("sans")) {
+		"Times-Roman"
+	} else {
+		"Helvetica"
+	}
+}
+
+// Six capital letters made from the glyphs in the subset, so different subsets get different tags
+fn subset_tag(glyphs: &BTreeMap<u16, char>) -> String {
+	let hash = glyphs.keys().fold(5381u32, |hash, glyph| hash.wrapping_mul(33) ^ *glyph as u32);
+	(0..6).map(|i| (b'A' + (hash / 26u32.pow(i) % 26) as u8) as char).collect()
+}
+
+// PDF font names can't have spaces
+fn postscript_name(face: &Face) -> String {
+	face.names()
+		.into_iter()
+		.filter(|name| name.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+		.find_map(|name| name.to_string())
+		.unwrap_or_else(|| "EmbeddedFont".to_string())
+		.replace(' ', "")
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use image::RgbImage;
+	use lopdf::{Dictionary, Document, Object};
+	use crate::file_details::FileDetails;
+	use crate::page_items::PageItemsBuilder;
+	use crate::text_details::TextDetailsBuilder;
+	use super::super::font_subset::tests::test_font;
+	use super::super::settings::{CanvasSettingsBuilder, Unit};
+	use super::*;
+
+	fn png() -> Vec<u8> {
+		let mut data = Vec::new();
+		RgbImage::from_pixel(4, 2, image::Rgb([255, 0, 0])).write_to(&mut Cursor::new(&mut data), ImageFormat::Png).unwrap();
+		data
+	}
+
+	fn text_item(id: u32, text: &str, font_family: &str) -> PageItems {
+		let text = TextDetailsBuilder::default()
+			.text(text.to_string())
+			.font_size(16)
+			.font_family(font_family.to_string())
+			.font_color("#000000".to_string())
+			.build().unwrap();
+		PageItemsBuilder::default().id(id).text(Some(text)).x(10.0).y(10.0).width(Some(100.0)).height(Some(30.0)).build().unwrap()
+	}
+
+	fn resolve<'a>(document: &'a Document, object: &'a Object) -> &'a Object {
+		match object {
+			Object::Reference(id) => document.get_object(*id).unwrap(),
+			_ => object,
+		}
+	}
+
+	fn page_fonts(document: &Document, page: u32) -> Vec<Dictionary> {
+		let page_id = document.get_pages()[&page];
+		document.get_page_fonts(page_id).unwrap().into_values().cloned().collect()
+	}
+
+	#[test]
+	fn pages_images_and_standard_fonts() {
+		let settings = CanvasSettingsBuilder::default()
+			.width(Some(100.0))
+			.height(Some(50.0))
+			.unit(Unit::Mm)
+			.background_color(Some("#ffeeee".to_string()))
+			.build().unwrap();
+		let file = FileDetails {
+			name: "photo.png".to_string(),
+			file_type: "image/png".to_string(),
+			data: png(),
+			width: 4,
+			height: 2,
+			source: None,
+		};
+		let first = vec![
+			PageItemsBuilder::default().id(1).file(Some(file)).x(0.0).y(0.0).width(Some(40.0)).build().unwrap(),
+			text_item(2, "Hello", "Times New Roman"),
+		];
+		let second = vec![text_item(3, "World", "Arial")];
+		let pages = [PdfPage { settings: &settings, items: &first }, PdfPage { settings: &settings, items: &second }];
+
+		let pdf = export_pdf(&pages, &PdfResources::default()).unwrap();
+		let document = Document::load_mem(&pdf).unwrap();
+
+		assert_eq!(document.get_pages().len(), 2);
+		let content = String::from_utf8_lossy(&document.get_page_content(document.get_pages()[&1]).unwrap()).to_string();
+		assert!(content.contains("(Hello) Tj"), "{}", content);
+		assert!(content.contains(" Do"), "The image should be drawn: {}", content);
+
+		let base_fonts: Vec<_> = [1, 2].iter()
+			.flat_map(|page| page_fonts(&document, *page))
+			.map(|font| font.get(b"BaseFont").unwrap().as_name_str().unwrap().to_string())
+			.collect();
+		assert_eq!(base_fonts, ["Times-Roman", "Helvetica"]);
+	}
+
+	#[test]
+	fn embedded_fonts_are_subset() {
+		let settings = CanvasSettingsBuilder::default().width(Some(200.0)).height(Some(100.0)).build().unwrap();
+		let items = vec![text_item(1, "A", "Test Sans")];
+		let resources = PdfResources { fonts: HashMap::from([("Test Sans".to_string(), test_font())]), ..Default::default() };
+
+		let pdf = export_pdf(&[PdfPage { settings: &settings, items: &items }], &resources).unwrap();
+		let document = Document::load_mem(&pdf).unwrap();
+
+		let fonts = page_fonts(&document, 1);
+		let base_font = fonts[0].get(b"BaseFont").unwrap().as_name_str().unwrap();
+		assert!(base_font.len() > 7 && base_font.as_bytes()[6] == b'+', "{} should have a subset tag", base_font);
+
+		let descendant = resolve(&document, &fonts[0].get(b"DescendantFonts").unwrap().as_array().unwrap()[0]).as_dict().unwrap();
+		let descriptor = resolve(&document, descendant.get(b"FontDescriptor").unwrap()).as_dict().unwrap();
+		let font_file = resolve(&document, descriptor.get(b"FontFile2").unwrap()).as_stream().unwrap();
+		let data = font_file.decompressed_content().unwrap();
+
+		let face = Face::parse(&data, 0).unwrap();
+		assert!(face.glyph_bounding_box(GlyphId(1)).is_some(), "A should be kept");
+		assert!(face.glyph_bounding_box(GlyphId(2)).is_none(), "B isn't used");
+		assert_eq!(font_file.dict.get(b"Length1").unwrap().as_i64().unwrap(), data.len() as i64);
+	}
+}
@@ -0,0 +1,120 @@
+// Checks that items on the canvas will print well
+// Images need a good enough resolution, which needs the canvas to use physical units, otherwise
+// there's no way to know how big an image will be once printed. Everything should also stay
+// inside the safe area so it isn't cut off
+use serde::Serialize;
+use crate::page_items::PageItems;
+use super::settings::{Area, CanvasSettings};
+
+#[derive(Clone, PartialEq, Serialize, Debug)]
+pub struct DpiWarning {
+	pub item_id: u32,
+	pub name: String,
+	pub dpi: f64,
+	pub min_dpi: f64,
+}
+
+#[derive(Clone, PartialEq, Serialize, Debug)]
+pub struct SafeZoneWarning {
+	pub item_id: u32,
+}
+
+// Get the area an item covers in canvas units. This matches how the components size themselves
+// when only one or neither dimension is given
+pub fn item_area(item: &PageItems, settings: &CanvasSettings) -> Area {
+	let width = item.width.filter(|w| *w > 0.0);
+	let height = item.height.filter(|h| *h > 0.0);
+
+	let (width, height) = match &item.file {
+		Some(file) if file.width > 0 && file.height > 0 => file.scaled_size(width, height)
+			.unwrap_or_else(|| (settings.from_screen(file.width), settings.from_screen(file.height))),
+		_ => {
+			// Movable items default to 250x250 and static ones to 100x100
+			let default_size = settings.from_screen(if item.movable { 250 } else { 100 });
+			(width.unwrap_or(default_size), height.unwrap_or(default_size))
+		}
+	};
+
+	Area {
+		x: item.x,
+		y: item.y,
+		width,
+		height,
+	}
+}
+
+// Get the resolution an image item will be printed at, in dots per inch
+// Returns None if the item isn't an image, or the canvas doesn't have a physical size
+pub fn effective_dpi(item: &PageItems, settings: &CanvasSettings) -> Option<f64> {
+	let file = item.file.as_ref()?;
+	if file.width <= 0 || file.height <= 0 {
+		return None;
+	}
+
+	let area = item_area(item, settings);
+	let dpi_x = file.width as f64 / settings.to_inches(area.width)?;
+	let dpi_y = file.height as f64 / settings.to_inches(area.height)?;
+	// Covering a frame uses the smaller of these, and zooming in spreads the pixels out further
+	let zoom = if item.fills_frame() { item.crop.zoom.max(1.0) } else { 1.0 };
+	Some(dpi_x.min(dpi_y) / zoom)
+}
+
+// Check every image on the canvas, returning a warning for each one below the minimum DPI
+pub fn check_print_quality(items: &[PageItems], settings: &CanvasSettings) -> Vec<DpiWarning> {
+	items.iter()
+		.filter_map(|item| {
+			let dpi = effective_dpi(item, settings)?;
+			if dpi >= settings.min_dpi {
+				return None;
+			}
+
+			Some(DpiWarning {
+				item_id: item.id,
+				name: item.file.as_ref().map(|file| file.name.clone()).unwrap_or_default(),
+				dpi,
+				min_dpi: settings.min_dpi,
+			})
+		})
+		.collect()
+}
+
+// Find every item that reaches outside the safe area
+pub fn check_safe_zone(items: &[PageItems], settings: &CanvasSettings) -> Vec<SafeZoneWarning> {
+	// Nothing to check if there's no bleed or safe margin
+	if settings.bleed_mm <= 0.0 && settings.safe_mm <= 0.0 {
+		return Vec::new();
+	}
+
+	let safe_area = settings.safe_area();
+	items.iter()
+		.filter(|item| !safe_area.contains(&item_area(item, settings)))
+		.map(|item| SafeZoneWarning { item_id: item.id })
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::file_details::FileDetails;
+	use crate::page_items::PageItemsBuilder;
+	use super::super::settings::{CanvasSettingsBuilder, Unit};
+	use super::*;
+
+	#[test]
+	fn images_with_only_a_width_keep_their_aspect_ratio() {
+		let settings = CanvasSettingsBuilder::default().unit(Unit::In).width(Some(10.0)).height(Some(10.0)).build().unwrap();
+		// Twice as wide as it is tall
+		let file = FileDetails {
+			name: "photo.png".to_string(),
+			file_type: "image/png".to_string(),
+			data: vec![],
+			width: 600,
+			height: 300,
+			source: None,
+		};
+		let item = PageItemsBuilder::default().file(Some(file)).width(Some(4.0)).build().unwrap();
+
+		let area = item_area(&item, &settings);
+		assert_eq!((area.width, area.height), (4.0, 2.0));
+		assert_eq!(effective_dpi(&item, &settings), Some(150.0));
+	}
+}
@@ -0,0 +1,368 @@
+// Draws shapes and text into pixels without the browser, so image exports come out the same
+// everywhere. Curves are flattened into lines, and shapes are filled with the nonzero rule
+use image::{GrayImage, Luma, Rgba, RgbaImage};
+use svgtypes::{Color, SimplePathSegment, SimplifyingPathParser};
+use ttf_parser::{Face, OutlineBuilder};
+
+// Lines each curve is split into
+const CURVE_STEPS: usize = 16;
+// Rows sampled in each pixel. Coverage across a row is worked out exactly
+const SUBSAMPLES: usize = 4;
+// Line height used by the text components, as a multiple of the font size
+pub const LINE_HEIGHT: f64 = 1.2;
+
+// Closed shapes made of straight lines, in pixels
+#[derive(Default)]
+pub struct Outline {
+	shapes: Vec<Vec<(f64, f64)>>,
+	// Where points go: x and y are scaled then moved, with y flipped for font units
+	scale_x: f64,
+	scale_y: f64,
+	offset_x: f64,
+	offset_y: f64,
+}
+
+impl Outline {
+	pub fn new() -> Self {
+		Self { scale_x: 1.0, scale_y: 1.0, ..Default::default() }
+	}
+
+	// Add an SVG path, scaled from path units to pixels
+	pub fn add_svg_path(&mut self, path: &str, scale_x: f64, scale_y: f64) -> Result<(), String> {
+		self.set_transform(scale_x, scale_y, 0.0, 0.0);
+
+		for segment in SimplifyingPathParser::from(path) {
+			match segment.map_err(|err| format!("Invalid mask path: {}", err))? {
+				SimplePathSegment::MoveTo { x, y } => self.move_to(x, y),
+				SimplePathSegment::LineTo { x, y } => self.line_to(x, y),
+				SimplePathSegment::CurveTo { x1, y1, x2, y2, x, y } => self.cubic_to(x1, y1, x2, y2, x, y),
+				SimplePathSegment::Quadratic { x1, y1, x, y } => self.quad_to(x1, y1, x, y),
+				SimplePathSegment::ClosePath => self.close(),
+			}
+		}
+
+		Ok(())
+	}
+
+	// Add a glyph with its origin at x and the baseline, scaled from font units to pixels
+	pub fn add_glyph(&mut self, face: &Face, glyph: ttf_parser::GlyphId, x: f64, baseline: f64, scale: f64) {
+		self.set_transform(scale, -scale, x, baseline);
+		face.outline_glyph(glyph, self);
+	}
+
+	fn set_transform(&mut self, scale_x: f64, scale_y: f64, offset_x: f64, offset_y: f64) {
+		self.scale_x = scale_x;
+		self.scale_y = scale_y;
+		self.offset_x = offset_x;
+		self.offset_y = offset_y;
+	}
+
+	fn point(&self, x: f64, y: f64) -> (f64, f64) {
+		(x * self.scale_x + self.offset_x, y * self.scale_y + self.offset_y)
+	}
+
+	// Last point, in the units points are given in
+	fn current(&self) -> (f64, f64) {
+		let (x, y) = self.shapes.last().and_then(|shape| shape.last().copied()).unwrap_or((self.offset_x, self.offset_y));
+		((x - self.offset_x) / self.scale_x, (y - self.offset_y) / self.scale_y)
+	}
+
+	fn move_to(&mut self, x: f64, y: f64) {
+		let point = self.point(x, y);
+		self.shapes.push(vec![point]);
+	}
+
+	fn line_to(&mut self, x: f64, y: f64) {
+		let point = self.point(x, y);
+		match self.shapes.last_mut() {
+			Some(shape) => shape.push(point),
+			None => self.shapes.push(vec![point]),
+		}
+	}
+
+	fn quad_to(&mut self, x1: f64, y1: f64, x: f64, y: f64) {
+		let (x0, y0) = self.current();
+		for step in 1..=CURVE_STEPS {
+			let t = step as f64 / CURVE_STEPS as f64;
+			let u = 1.0 - t;
+			self.line_to(
+				u * u * x0 + 2.0 * u * t * x1 + t * t * x,
+				u * u * y0 + 2.0 * u * t * y1 + t * t * y,
+			);
+		}
+	}
+
+	fn cubic_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, x: f64, y: f64) {
+		let (x0, y0) = self.current();
+		for step in 1..=CURVE_STEPS {
+			let t = step as f64 / CURVE_STEPS as f64;
+			let u = 1.0 - t;
+			self.line_to(
+				u * u * u * x0 + 3.0 * u * u * t * x1 + 3.0 * u * t * t * x2 + t * t * t * x,
+				u * u * u * y0 + 3.0 * u * u * t * y1 + 3.0 * u * t * t * y2 + t * t * t * y,
+			);
+		}
+	}
+
+	// Shapes are always filled as if they were closed, so closing just starts a new shape
+	fn close(&mut self) {
+		if let Some(first) = self.shapes.last().and_then(|shape| shape.first().copied()) {
+			self.shapes.push(vec![first]);
+		}
+	}
+
+	// How much of each pixel the shapes cover, from 0 to 255
+	pub fn fill(&self, width: u32, height: u32) -> GrayImage {
+		let mut coverage = vec![0.0f64; (width * height) as usize];
+		let edges: Vec<((f64, f64), (f64, f64))> = self.shapes.iter()
+			.filter(|shape| shape.len() > 1)
+			.flat_map(|shape| shape.iter().copied().zip(shape.iter().copied().cycle().skip(1)))
+			.filter(|((_, y0), (_, y1))| y0 != y1)
+			.collect();
+
+		let mut crossings = Vec::new();
+		for row in 0..height {
+			for sample in 0..SUBSAMPLES {
+				let y = row as f64 + (sample as f64 + 0.5) / SUBSAMPLES as f64;
+
+				// Where each edge crosses this row, and which way it's going
+				crossings.clear();
+				for &((x0, y0), (x1, y1)) in &edges {
+					if (y0 <= y) != (y1 <= y) {
+						let x = x0 + (y - y0) / (y1 - y0) * (x1 - x0);
+						crossings.push((x, if y1 > y0 { 1 } else { -1 }));
+					}
+				}
+				crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+				let mut winding = 0;
+				for pair in crossings.windows(2) {
+					winding += pair[0].1;
+					if winding != 0 {
+						add_span(&mut coverage[(row * width) as usize..((row + 1) * width) as usize], pair[0].0, pair[1].0);
+					}
+				}
+			}
+		}
+
+		GrayImage::from_fn(width, height, |x, y| {
+			let value = coverage[(y * width + x) as usize] / SUBSAMPLES as f64;
+			Luma([(value.clamp(0.0, 1.0) * 255.0).round() as u8])
+		})
+	}
+}
+
+impl OutlineBuilder for Outline {
+	fn move_to(&mut self, x: f32, y: f32) {
+		Outline::move_to(self, x as f64, y as f64);
+	}
+
+	fn line_to(&mut self, x: f32, y: f32) {
+		Outline::line_to(self, x as f64, y as f64);
+	}
+
+	fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+		Outline::quad_to(self, x1 as f64, y1 as f64, x as f64, y as f64);
+	}
+
+	fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+		Outline::cubic_to(self, x1 as f64, y1 as f64, x2 as f64, y2 as f64, x as f64, y as f64);
+	}
+
+	fn close(&mut self) {
+		Outline::close(self);
+	}
+}
+
+// Add a filled span of one sample row, counting how much of the pixels at each end it covers
+fn add_span(row: &mut [f64], start: f64, end: f64) {
+	let start = start.clamp(0.0, row.len() as f64);
+	let end = end.clamp(0.0, row.len() as f64);
+	if end <= start {
+		return;
+	}
+
+	let first = start.floor() as usize;
+	let last = end.ceil() as usize;
+	for (x, pixel) in row.iter_mut().enumerate().take(last).skip(first) {
+		let covered = end.min(x as f64 + 1.0) - start.max(x as f64);
+		*pixel += covered;
+	}
+}
+
+// Paint a color over an image wherever the coverage says to
+pub fn paint(image: &mut RgbaImage, coverage: &GrayImage, color: Rgba<u8>, x: i64, y: i64) {
+	for (cx, cy, amount) in coverage.enumerate_pixels() {
+		let (px, py) = (x + cx as i64, y + cy as i64);
+		if amount.0[0] == 0 || px < 0 || py < 0 || px >= image.width() as i64 || py >= image.height() as i64 {
+			continue;
+		}
+		let alpha = (color.0[3] as u16 * amount.0[0] as u16 / 255) as u8;
+		blend(image.get_pixel_mut(px as u32, py as u32), Rgba([color.0[0], color.0[1], color.0[2], alpha]));
+	}
+}
+
+// Source over compositing, the same as a canvas does by default
+pub fn blend(pixel: &mut Rgba<u8>, color: Rgba<u8>) {
+	let alpha = color.0[3] as f64 / 255.0;
+	let below = pixel.0[3] as f64 / 255.0;
+	let out = alpha + below * (1.0 - alpha);
+	if out == 0.0 {
+		*pixel = Rgba([0, 0, 0, 0]);
+		return;
+	}
+
+	for i in 0..3 {
+		let value = (color.0[i] as f64 * alpha + pixel.0[i] as f64 * below * (1.0 - alpha)) / out;
+		pixel.0[i] = value.round() as u8;
+	}
+	pixel.0[3] = (out * 255.0).round() as u8;
+}
+
+// Any CSS color. The page ignores colors it can't read, so callers decide what to use instead
+pub fn parse_color(color: &str) -> Option<Rgba<u8>> {
+	let color = color.parse::<Color>().ok()?;
+	Some(Rgba([color.red, color.green, color.blue, color.alpha]))
+}
+
+// Split text into lines the way a textarea does: at line breaks, then between words when a line
+// is wider than the box. Words too wide for the box on their own are broken between characters
+pub fn wrap_text(text: &str, width: f64, measure: impl Fn(&str) -> f64) -> Vec<String> {
+	let mut lines = Vec::new();
+
+	for paragraph in text.split('\n') {
+		let mut line = String::new();
+		for word in paragraph.split_inclusive(' ') {
+			let candidate = format!("{}{}", line, word);
+			if line.is_empty() || measure(candidate.trim_end()) <= width {
+				line = candidate;
+			} else {
+				lines.push(line.trim_end().to_string());
+				line = word.to_string();
+			}
+
+			// Break up a word that doesn't fit on a line of its own
+			while measure(line.trim_end()) > width && line.trim_end().chars().count() > 1 {
+				let mut split = line.char_indices().nth(1).map(|(i, _)| i).unwrap_or(line.len());
+				for (i, _) in line.char_indices().skip(2) {
+					if measure(&line[..i]) > width {
+						break;
+					}
+					split = i;
+				}
+				lines.push(line[..split].to_string());
+				line = line[split..].to_string();
+			}
+		}
+		lines.push(line.trim_end().to_string());
+	}
+
+	lines
+}
+
+// Draw text in a font file, wrapped to the width of the box. Returns how much of each pixel the
+// text covers, so it can be painted in any color
+pub fn render_text(face: &Face, text: &str, font_size: f64, width: u32, height: u32) -> GrayImage {
+	let scale = font_size / face.units_per_em() as f64;
+	let advance = |c: char| {
+		let glyph = face.glyph_index(c).unwrap_or_default();
+		face.glyph_hor_advance(glyph).unwrap_or(0) as f64 * scale
+	};
+	let measure = |line: &str| line.chars().map(advance).sum::<f64>();
+
+	let mut outline = Outline::new();
+	let ascent = face.ascender() as f64 * scale;
+	for (i, line) in wrap_text(text, width as f64, measure).iter().enumerate() {
+		let baseline = i as f64 * font_size * LINE_HEIGHT + ascent;
+		let mut x = 0.0;
+		for c in line.chars() {
+			outline.add_glyph(face, face.glyph_index(c).unwrap_or_default(), x, baseline, scale);
+			x += advance(c);
+		}
+	}
+
+	outline.fill(width, height)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::font_subset::tests::test_font;
+	use super::*;
+
+	// Every character is 10 pixels wide
+	fn measure(text: &str) -> f64 {
+		text.chars().count() as f64 * 10.0
+	}
+
+	#[test]
+	fn wraps_between_words() {
+		assert_eq!(wrap_text("one two three", 75.0, measure), ["one two", "three"]);
+	}
+
+	#[test]
+	fn keeps_line_breaks() {
+		assert_eq!(wrap_text("one\n\ntwo", 100.0, measure), ["one", "", "two"]);
+	}
+
+	#[test]
+	fn breaks_long_words() {
+		assert_eq!(wrap_text("abcdefgh ij", 30.0, measure), ["abc", "def", "gh", "ij"]);
+	}
+
+	#[test]
+	fn fills_rectangles() {
+		let mut outline = Outline::new();
+		outline.add_svg_path("M 1 1 L 3 1 L 3 3 L 1 3 Z", 1.0, 1.0).unwrap();
+		let coverage = outline.fill(4, 4);
+
+		assert_eq!(coverage.get_pixel(0, 0).0[0], 0);
+		assert_eq!(coverage.get_pixel(1, 1).0[0], 255);
+		assert_eq!(coverage.get_pixel(2, 2).0[0], 255);
+		assert_eq!(coverage.get_pixel(3, 3).0[0], 0);
+	}
+
+	#[test]
+	fn antialiases_edges() {
+		let mut outline = Outline::new();
+		outline.add_svg_path("M 0 0 L 1.5 0 L 1.5 1 L 0 1 Z", 1.0, 1.0).unwrap();
+		let coverage = outline.fill(2, 1);
+
+		assert_eq!(coverage.get_pixel(0, 0).0[0], 255);
+		assert_eq!(coverage.get_pixel(1, 0).0[0], 128);
+	}
+
+	#[test]
+	fn scales_paths() {
+		let mut outline = Outline::new();
+		outline.add_svg_path("M 0 0 L 1 0 L 1 1 Z", 4.0, 2.0).unwrap();
+		let coverage = outline.fill(4, 2);
+
+		assert_eq!(coverage.get_pixel(3, 0).0[0], 255);
+		assert_eq!(coverage.get_pixel(0, 1).0[0], 0);
+	}
+
+	#[test]
+	fn renders_text_in_font_files() {
+		// A is a triangle 100 units wide and tall, with an advance of 500 units
+		let font = test_font();
+		let face = Face::parse(&font, 0).unwrap();
+		let coverage = render_text(&face, "A A", 100.0, 60, 200);
+
+		// The baseline is at the font's ascent, 80 pixels down, and the second A wraps onto the next line
+		assert_eq!(coverage.get_pixel(5, 79).0[0], 255);
+		assert_eq!(coverage.get_pixel(5, 60).0[0], 0);
+		assert_eq!(coverage.get_pixel(55, 79).0[0], 0);
+		assert_eq!(coverage.get_pixel(5, 79 + 120).0[0], 255);
+	}
+
+	#[test]
+	fn blends_colors() {
+		let mut pixel = Rgba([0, 0, 255, 255]);
+		blend(&mut pixel, Rgba([255, 0, 0, 128]));
+		assert_eq!(pixel, Rgba([128, 0, 127, 255]));
+
+		let mut pixel = Rgba([0, 0, 0, 0]);
+		blend(&mut pixel, parse_color("rgba(255, 0, 0, 0.5)").unwrap());
+		assert_eq!(pixel, Rgba([255, 0, 0, 128]));
+	}
+}
@@ -0,0 +1,34 @@
+// Keeps keyboard focus on the selected item, so keys work on whatever is selected, whether it was
+// selected with the mouse or with Tab
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlElement;
+use yew::prelude::*;
+
+// Javascript functions
+#[wasm_bindgen]
+extern "C" {
+	// Focuses without scrolling the page, which can't be done from Rust yet
+	#[wasm_bindgen(js_name = focusElement)]
+	fn focus_element(element: &HtmlElement);
+}
+
+// Focus the element when it's selected. When it's deselected while it has focus, focus goes back to
+// whatever contains it, so the keyboard still works
+#[hook]
+pub fn use_selection_focus(node_ref: NodeRef, selected: bool) {
+	use_effect_with(selected, move |selected| {
+		if let Some(element) = node_ref.cast::<HtmlElement>() {
+			let focused = element.matches(":focus").unwrap_or(false);
+			if *selected && !focused {
+				focus_element(&element);
+			} else if !*selected && focused {
+				match element.parent_element().and_then(|parent| parent.closest("[tabindex]").ok().flatten()) {
+					Some(container) => focus_element(container.unchecked_ref()),
+					None => {
+						let _ = element.blur();
+					}
+				}
+			}
+		}
+	});
+}
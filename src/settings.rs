@@ -1,10 +1,15 @@
 // Functions/structs that configure/contain the settings
 use derive_builder::Builder;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use svgtypes::SimplifyingPathParser;
 use wasm_bindgen::prelude::*;
-use crate::console;
-use crate::page_items::PageItems;
+use crate::page_items::{ImageCrop, ItemRole, PageItems, next_item_id};
 use crate::text_details::TextDetailsBuilder;
 use crate::file_details::FileDetails;
+use super::design_link::{DESIGN_PARAM, decode_design};
+use super::surface::SurfaceDesign;
 
 // Javascript functions
 #[wasm_bindgen]
@@ -13,23 +18,337 @@ extern "C" {
 	fn get_query_param(param: &str) -> Vec<String>;
 }
 
-#[derive(PartialEq, Clone, Builder)]
+// Units the canvas size and item geometry are measured in
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+	Px,
+	Mm,
+	In,
+}
+
+impl Unit {
+	// Returns None for pixels, since they don't have a physical size
+	pub fn inches_per_unit(&self) -> Option<f64> {
+		match self {
+			Unit::Px => None,
+			Unit::Mm => Some(1.0 / 25.4),
+			Unit::In => Some(1.0),
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Unit::Px => "px",
+			Unit::Mm => "mm",
+			Unit::In => "in",
+		}
+	}
+
+	pub fn parse(unit: &str) -> Option<Unit> {
+		match unit.to_lowercase().as_str() {
+			"px" => Some(Unit::Px),
+			"mm" => Some(Unit::Mm),
+			"in" => Some(Unit::In),
+			_ => None,
+		}
+	}
+}
+
+// Default size of the canvas on screen, in pixels
+pub static DEFAULT_CANVAS_SIZE: i32 = 800;
+
+// CSS pixels per inch
+static CSS_PX_PER_INCH: f64 = 96.0;
+
+// Name of the surface used when none are set up
+pub static DEFAULT_SURFACE: &str = "default";
+
+#[derive(PartialEq, Clone, Builder, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CanvasSettings {
+	// Size of the canvas, in `unit`s. Item geometry is stored in the same units
+	#[builder(default)]
+	pub width: Option<f64>,
+	#[builder(default)]
+	pub height: Option<f64>,
+	#[builder(default = "Unit::Px")]
+	pub unit: Unit,
+	// Resolution the design is exported at, when the canvas has a physical size
+	#[builder(default = "300.0")]
+	pub dpi: f64,
+	// Width of the canvas on screen in pixels, when the canvas has a physical size. The height is
+	// worked out from the aspect ratio
+	#[builder(default)]
+	pub display_width: Option<i32>,
+	// Images printed below this many dots per inch get a warning
+	#[builder(default = "150.0")]
+	pub min_dpi: f64,
+	// Extra area past the trim line on every side, which gets cut off after printing. In millimetres
+	#[builder(default = "0.0")]
+	pub bleed_mm: f64,
+	// Distance inside the trim line that important content should stay within. In millimetres
+	#[builder(default = "0.0")]
+	pub safe_mm: f64,
+	// Whether exports include the bleed area, or are cropped to the trim line
+	#[builder(default = "true")]
+	pub export_bleed: bool,
+	// Shape of the printable area, as an SVG path in canvas units. Anything outside it is hidden
 	#[builder(default)]
-	pub width: Option<i32>,
+	pub mask_path: Option<String>,
+	// URL of an image whose alpha channel is the printable area. Used if there's no mask path
 	#[builder(default)]
-	pub height: Option<i32>,
+	pub mask_image: Option<String>,
+	// Shown behind every item. The image can't be moved or deleted
+	#[builder(default)]
+	pub background_color: Option<String>,
+	#[builder(default)]
+	pub background_image: Option<String>,
+	// Leave the background out of exports, so anything not covered by an item is transparent
+	#[builder(default = "false")]
+	pub transparent_export: bool,
+}
+
+// Settings for the editor's own Save button
+#[derive(PartialEq, Clone, Deserialize)]
+#[serde(default)]
+pub struct SaveSettings {
+	// Whether the Save button is shown. The host page can still export without it
+	pub show_button: bool,
+	// Whether saving downloads the file. The export is sent to the host page either way
+	pub download: bool,
+}
+
+impl Default for SaveSettings {
+	fn default() -> Self {
+		Self {
+			show_button: true,
+			download: true,
+		}
+	}
+}
+
+// A rectangle in canvas units
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Area {
+	pub x: f64,
+	pub y: f64,
+	pub width: f64,
+	pub height: f64,
+}
+
+impl Area {
+	pub fn contains(&self, other: &Area) -> bool {
+		other.x >= self.x && other.y >= self.y
+			&& other.x + other.width <= self.x + self.width
+			&& other.y + other.height <= self.y + self.height
+	}
+}
+
+impl Default for CanvasSettings {
+	fn default() -> Self {
+		CanvasSettingsBuilder::default().build().unwrap()
+	}
+}
+
+impl CanvasSettings {
+	pub fn width_units(&self) -> f64 {
+		self.width.unwrap_or(self.from_screen(DEFAULT_CANVAS_SIZE))
+	}
+
+	pub fn height_units(&self) -> f64 {
+		self.height.unwrap_or(self.from_screen(DEFAULT_CANVAS_SIZE))
+	}
+
+	// Convert millimetres to canvas units. Pixel canvases use the target DPI
+	pub fn mm_to_units(&self, mm: f64) -> f64 {
+		let inches = mm / 25.4;
+		match self.unit.inches_per_unit() {
+			Some(inches_per_unit) => inches / inches_per_unit,
+			None => inches * self.dpi,
+		}
+	}
+
+	pub fn bleed_units(&self) -> f64 {
+		self.mm_to_units(self.bleed_mm)
+	}
+
+	// The width and height are the trim size. The canvas is this plus the bleed on both sides
+	pub fn full_width_units(&self) -> f64 {
+		self.width_units() + 2.0 * self.bleed_units()
+	}
+
+	pub fn full_height_units(&self) -> f64 {
+		self.height_units() + 2.0 * self.bleed_units()
+	}
+
+	// Area of the canvas that's left after the bleed is cut off
+	pub fn trim_area(&self) -> Area {
+		Area {
+			x: self.bleed_units(),
+			y: self.bleed_units(),
+			width: self.width_units(),
+			height: self.height_units(),
+		}
+	}
+
+	// Area of the canvas that's safe from being cut off
+	pub fn safe_area(&self) -> Area {
+		let margin = self.bleed_units() + self.mm_to_units(self.safe_mm);
+		Area {
+			x: margin,
+			y: margin,
+			width: self.full_width_units() - 2.0 * margin,
+			height: self.full_height_units() - 2.0 * margin,
+		}
+	}
+
+	// Screen pixels per canvas unit
+	pub fn px_per_unit(&self) -> f64 {
+		match (self.unit.inches_per_unit(), self.width) {
+			(None, _) => 1.0,
+			(Some(_), Some(width)) => self.display_width.unwrap_or(DEFAULT_CANVAS_SIZE) as f64 / (width + 2.0 * self.bleed_units()),
+			// validate stops this happening. Should it anyway, use the size CSS gives the unit
+			(Some(inches), None) => inches * CSS_PX_PER_INCH,
+		}
+	}
+
+	// A canvas in physical units needs a width to work out how big it is on screen
+	pub fn missing_width(&self) -> bool {
+		self.unit != Unit::Px && self.width.is_none()
+	}
+
+	pub fn validate(&self) -> Result<(), String> {
+		if self.missing_width() {
+			return Err(format!("A canvas in {} needs a width", self.unit.name()));
+		}
+		if let Some(path) = &self.mask_path {
+			check_mask_path(path)?;
+		}
+		Ok(())
+	}
+
+	// Convert a length in canvas units to screen pixels
+	pub fn to_screen(&self, value: f64) -> i32 {
+		(value * self.px_per_unit()).round() as i32
+	}
+
+	// Convert a length in screen pixels to canvas units
+	pub fn from_screen(&self, pixels: i32) -> f64 {
+		pixels as f64 / self.px_per_unit()
+	}
+
+	pub fn screen_width(&self) -> i32 {
+		self.to_screen(self.full_width_units())
+	}
+
+	pub fn screen_height(&self) -> i32 {
+		self.to_screen(self.full_height_units())
+	}
+
+	// Convert a length in canvas units to inches. None if the canvas has no physical size
+	pub fn to_inches(&self, value: f64) -> Option<f64> {
+		self.unit.inches_per_unit().map(|inches| value * inches)
+	}
+
+	// URL of the image to mask the canvas with, if it has a printable area set
+	pub fn mask_url(&self) -> Option<String> {
+		if let Some(path) = self.mask_path.as_ref().filter(|path| check_mask_path(path).is_ok()) {
+			// Stretch the path's coordinates over the whole canvas, including the bleed
+			let svg = format!(
+				"<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" preserveAspectRatio=\"none\"><path d=\"{}\" /></svg>",
+				self.full_width_units(),
+				self.full_height_units(),
+				path,
+			);
+			return Some(format!("data:image/svg+xml,{}", js_sys::encode_uri_component(&svg)));
+		}
+
+		self.mask_image.clone()
+	}
+
+	// How much the on screen canvas needs to be scaled up to export it at the target DPI
+	pub fn export_scale(&self) -> f64 {
+		match self.to_inches(1.0) {
+			Some(inches_per_unit) => self.dpi * inches_per_unit / self.px_per_unit(),
+			None => 1.0,
+		}
+	}
+}
+
+// A problem with the query string. Anything with a problem is left out or uses its default
+#[derive(Serialize, PartialEq, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QueryError {
+	// index is the item the value is for. Settings don't have one
+	UnknownType { index: usize, value: String },
+	BadNumber { param: String, index: Option<usize>, value: String },
+	BadValue { param: String, index: Option<usize>, value: String },
+	InvalidBase64 { index: usize, name: String },
+	UndecodableImage { index: usize, name: String },
+	// One of the item arrays doesn't have a value for every item it applies to
+	MismatchedLengths { param: String, expected: usize, found: usize },
+	InvalidDesign { message: String },
+	// The canvas is in physical units but has no width, so the unit isn't used
+	MissingWidth { unit: Unit },
+}
+
+impl std::fmt::Display for QueryError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			QueryError::UnknownType { index, value } => write!(f, "Item {} has an unknown type: {}", index, value),
+			QueryError::BadNumber { param, index: Some(index), value } => write!(f, "{} for item {} isn't a number: {}", param, index, value),
+			QueryError::BadNumber { param, index: None, value } => write!(f, "{} isn't a number: {}", param, value),
+			QueryError::BadValue { param, index: Some(index), value } => write!(f, "{} for item {} isn't valid: {}", param, index, value),
+			QueryError::BadValue { param, index: None, value } => write!(f, "{} isn't valid: {}", param, value),
+			QueryError::InvalidBase64 { index, name } => write!(f, "Image {} ({}) isn't valid base64", index, name),
+			QueryError::UndecodableImage { index, name } => write!(f, "Image {} ({}) couldn't be read", index, name),
+			QueryError::MismatchedLengths { param, expected, found } => write!(f, "{} has {} values, but there are {} items it applies to", param, found, expected),
+			QueryError::InvalidDesign { message } => write!(f, "The design in the link couldn't be loaded: {}", message),
+			QueryError::MissingWidth { unit } => write!(f, "canvas_width is needed for a canvas in {}", unit.name()),
+		}
+	}
+}
+
+// Parse a number from the query string. Empty values are left out without an error, since they're
+// used to pad the arrays
+fn parse_number<T: std::str::FromStr>(param: &str, index: Option<usize>, value: &str, errors: &mut Vec<QueryError>) -> Option<T> {
+	if value.is_empty() {
+		return None;
+	}
+	match value.parse::<T>() {
+		Ok(value) => Some(value),
+		Err(_) => {
+			errors.push(QueryError::BadNumber { param: param.to_string(), index, value: value.to_string() });
+			None
+		}
+	}
 }
 
-pub fn parse_query() -> Vec<PageItems> {
+fn parse_bool(param: &str, index: Option<usize>, value: &str, errors: &mut Vec<QueryError>) -> Option<bool> {
+	if value.is_empty() {
+		return None;
+	}
+	match value.parse::<bool>() {
+		Ok(value) => Some(value),
+		Err(_) => {
+			errors.push(QueryError::BadValue { param: param.to_string(), index, value: value.to_string() });
+			None
+		}
+	}
+}
+
+pub fn parse_query(errors: &mut Vec<QueryError>) -> Vec<(Option<String>, PageItems)> {
 	// This function will be used to parse the query string
 	// It will return a vector of PageItems which contains any text or file details to add to the
 	// page, as well as where to place them, their size, and if they are movable
+	// Each item is paired with the name of the surface it goes on, if one was given
+	// Problems are added to errors, and whatever had the problem uses its default
 	let mut items = Vec::new();
 
 	// Possible options:
 	// type
-	// value (either text, or base64 encoded image)
+	// value (either text, base64 encoded image, or the id or URL of an asset)
 	// x
 	// y
 	// width
@@ -41,11 +360,12 @@ pub fn parse_query() -> Vec<PageItems> {
 	// font_color (text only)
 	// background_color (text only)
 	// name (image only)
+	// surface
 	// real_width (image only)
 	// real_height (image only)
 	
-	// Each one will be an array. Corresponding values will be at the same index. If an attribute is
-	// only for text or image, adjust the length of the array accordingly (add empty strings for the other type)
+	// Each one will be an array. Corresponding values will be at the same index. Attributes that are
+	// only for text or images only have values for those items, in the same order
 	let types = get_query_param("type");
 	let values = get_query_param("value");
 	let x_values = get_query_param("x");
@@ -59,102 +379,55 @@ pub fn parse_query() -> Vec<PageItems> {
 	let font_color_values = get_query_param("font_color");
 	let background_color_values = get_query_param("background_color");
 	let name_values = get_query_param("name");
+	let surface_values = get_query_param("surface");
+
+	// Arrays that are given need a value for every item they apply to
+	let text_count = types.iter().filter(|item_type| *item_type == "text").count();
+	let image_count = types.iter().filter(|item_type| *item_type == "image" || *item_type == "asset").count();
+	let lengths = [
+		("value", &values, text_count.max(image_count)),
+		("x", &x_values, types.len()),
+		("y", &y_values, types.len()),
+		("width", &width_values, types.len()),
+		("height", &height_values, types.len()),
+		("movable", &movable_values, types.len()),
+		("surface", &surface_values, types.len()),
+		("editable", &editable_values, text_count),
+		("font_size", &font_size_values, text_count),
+		("font_family", &font_family_values, text_count),
+		("font_color", &font_color_values, text_count),
+		("background_color", &background_color_values, text_count),
+		("name", &name_values, image_count),
+	];
+	for (param, param_values, expected) in lengths {
+		if !param_values.is_empty() && param_values.len() != expected {
+			errors.push(QueryError::MismatchedLengths { param: param.to_string(), expected, found: param_values.len() });
+		}
+	}
 
-	let mut i = 0;
 	let mut text_i = 0; // Used for text only attributes
 	let mut image_i = 0; // Used for image only attributes
 	
-	while i < types.len() {
+	for (i, item_type) in types.iter().enumerate() {
 		let mut text = None;
 		let mut file = None;
-		
-		let x: i32;
-		let y: i32;
-		let width: Option<i32>;
-		let height: Option<i32>;
-		let movable: bool;
-
-		if x_values.len() > i {
-			x = x_values[i].parse::<i32>().unwrap_or(0);
-		} else {
-			x = 0;
-		}
-
-		if y_values.len() > i {
-			y = y_values[i].parse::<i32>().unwrap_or(0);
-		} else {
-			y = 0;
-		}
-
-		if width_values.len() > i {
-			width = match width_values[i].parse::<i32>() {
-				Ok(val) => Some(val),
-				Err(_) => None,
-			};
-		} else {
-			width = None;
-		}
-
-		if height_values.len() > i {
-			height = match height_values[i].parse::<i32>() {
-				Ok(val) => Some(val),
-				Err(_) => None,
-			};
-		} else {
-			height = None;
-		}
-
-		if movable_values.len() > i {
-			movable = movable_values[i].parse::<bool>().unwrap_or(false);
-		} else {
-			movable = false;
-		}
 
-		if types[i] == "text" {
-			let value: String;
-			let font_size: u16;
-			let font_family: String;
-			let font_color: String;
-			let background_color: String;
-			let editable: bool;
+		let index = Some(i);
+		let x = x_values.get(i).and_then(|val| parse_number("x", index, val, errors)).unwrap_or(0.0);
+		let y = y_values.get(i).and_then(|val| parse_number("y", index, val, errors)).unwrap_or(0.0);
+		let width = width_values.get(i).and_then(|val| parse_number("width", index, val, errors));
+		let height = height_values.get(i).and_then(|val| parse_number("height", index, val, errors));
+		let movable = movable_values.get(i).and_then(|val| parse_bool("movable", index, val, errors)).unwrap_or(false);
 
+		if item_type == "text" {
 			let default_text = TextDetailsBuilder::default().build().unwrap(); // To use as default values
 
-			if values.len() > text_i {
-				value = values[text_i].clone();
-			} else {
-				value = default_text.text;
-			}
-
-			if font_size_values.len() > text_i {
-				font_size = font_size_values[text_i].parse::<u16>().unwrap_or(default_text.font_size);
-			} else {
-				font_size = default_text.font_size;
-			}
-
-			if font_family_values.len() > text_i {
-				font_family = font_family_values[text_i].clone();
-			} else {
-				font_family = default_text.font_family;
-			}
-
-			if font_color_values.len() > text_i {
-				font_color = font_color_values[text_i].clone();
-			} else {
-				font_color = default_text.font_color;
-			}
-
-			if background_color_values.len() > text_i {
-				background_color = background_color_values[text_i].clone();
-			} else {
-				background_color = default_text.background_color;
-			}
-
-			if editable_values.len() > text_i {
-				editable = editable_values[text_i].parse::<bool>().unwrap_or(false);
-			} else {
-				editable = false;
-			}
+			let value = values.get(text_i).cloned().unwrap_or(default_text.text);
+			let font_size = font_size_values.get(text_i).and_then(|val| parse_number("font_size", index, val, errors)).unwrap_or(default_text.font_size);
+			let font_family = font_family_values.get(text_i).cloned().unwrap_or(default_text.font_family);
+			let font_color = font_color_values.get(text_i).cloned().unwrap_or(default_text.font_color);
+			let background_color = background_color_values.get(text_i).cloned().unwrap_or(default_text.background_color);
+			let editable = editable_values.get(text_i).and_then(|val| parse_bool("editable", index, val, errors)).unwrap_or(false);
 
 			text = Some(TextDetailsBuilder::default()
 				.text(value)
@@ -167,45 +440,62 @@ pub fn parse_query() -> Vec<PageItems> {
 			);
 
 			text_i += 1;
-		} else if types[i] == "image" {
-			let value: String;
-			let name: String;
-
-			if values.len() > image_i {
-				value = values[image_i].clone();
-			} else {
-				value = String::new();
-			}
+		} else if item_type == "image" {
+			let value = values.get(image_i).cloned().unwrap_or_default();
+			let name = name_values.get(image_i).cloned().unwrap_or_default();
 
-			if name_values.len() > image_i {
-				name = name_values[image_i].clone();
-			} else {
-				name = String::new();
-			}
+			let data = match STANDARD.decode(&value) {
+				Ok(data) => data,
+				Err(_) => {
+					errors.push(QueryError::InvalidBase64 { index: i, name: name.clone() });
+					Vec::new()
+				}
+			};
 
 			// Get width and height of image
-			let resolution_res = imagesize::blob_size(&base64::decode(value.clone()).unwrap_or(Vec::new()));
 			let mut real_width = 100;
 			let mut real_height = 100;
-
-			if let Ok(resolution) = resolution_res {
-				real_width = resolution.width as i32;
-				real_height = resolution.height as i32;
-			} else {
-				console::error_1(&format!("Failed to get resolution of image: {}. Using default", name).into());
+			match imagesize::blob_size(&data) {
+				Ok(resolution) => {
+					real_width = resolution.width as i32;
+					real_height = resolution.height as i32;
+				}
+				// Images that aren't valid base64 have already been reported
+				Err(_) if data.is_empty() => (),
+				Err(_) => errors.push(QueryError::UndecodableImage { index: i, name: name.clone() }),
 			}
 
 			file = Some(FileDetails {
 				name,
 				file_type: "image/png".to_string(),
-				data: base64::decode(value).unwrap_or(Vec::new()),
+				data,
 				width: real_width,
 				height: real_height,
+				source: None,
 			});
 			image_i += 1;
+		} else if item_type == "asset" {
+			// Loaded once the editor has started. The type and size are filled in then
+			let value = values.get(image_i).cloned().unwrap_or_default();
+			let name = name_values.get(image_i).cloned().unwrap_or_else(|| value.clone());
+
+			file = Some(FileDetails {
+				name,
+				file_type: String::new(),
+				data: Vec::new(),
+				width: 0,
+				height: 0,
+				source: Some(value),
+			});
+			image_i += 1;
+		} else {
+			errors.push(QueryError::UnknownType { index: i, value: item_type.clone() });
 		}
 
-		items.push(PageItems {
+		let surface = surface_values.get(i).filter(|surface| !surface.is_empty()).cloned();
+
+		items.push((surface, PageItems {
+			id: next_item_id(),
 			text,
 			file,
 			x,
@@ -213,29 +503,177 @@ pub fn parse_query() -> Vec<PageItems> {
 			width,
 			height,
 			movable,
-		});
-		i += 1;
+			role: ItemRole::Free,
+			crop: ImageCrop::default(),
+		}));
 	}
 
+	items
+}
+
+// Get a setting from the query string. A setting can be given for a single surface as
+// <surface>.<setting>, which overrides the setting given for every surface
+fn get_setting_param(param: &str, surface: Option<&str>) -> Option<String> {
+	if let Some(surface) = surface {
+		if let Some(val) = get_query_param(&format!("{}.{}", surface, param)).into_iter().next() {
+			return Some(val);
+		}
+	}
 
-	return items;
+	get_query_param(param).into_iter().next()
 }
 
-pub fn parse_settings_query() -> CanvasSettings {
-	let width = get_query_param("canvas_width");
-	let height = get_query_param("canvas_height");
+fn number_setting<T: std::str::FromStr>(param: &str, surface: Option<&str>, errors: &mut Vec<QueryError>) -> Option<T> {
+	get_setting_param(param, surface).and_then(|val| parse_number(param, None, &val, errors))
+}
 
-	let width = match width.get(0).unwrap_or(&String::new()).parse::<i32>() {
-		Ok(val) => Some(val),
-		Err(_) => None,
-	};
-	let height = match height.get(0).unwrap_or(&String::new()).parse::<i32>() {
-		Ok(val) => Some(val),
-		Err(_) => None,
+fn bool_setting(param: &str, surface: Option<&str>, errors: &mut Vec<QueryError>) -> Option<bool> {
+	get_setting_param(param, surface).and_then(|val| parse_bool(param, None, &val, errors))
+}
+
+// Override the settings in base with any given in the query string
+pub fn parse_settings_query(surface: Option<&str>, base: &CanvasSettings, errors: &mut Vec<QueryError>) -> CanvasSettings {
+	let unit = get_setting_param("canvas_unit", surface).and_then(|val| {
+		let unit = Unit::parse(&val);
+		if unit.is_none() {
+			errors.push(QueryError::BadValue { param: "canvas_unit".to_string(), index: None, value: val });
+		}
+		unit
+	});
+
+	let mut settings = CanvasSettings {
+		width: number_setting("canvas_width", surface, errors).or(base.width),
+		height: number_setting("canvas_height", surface, errors).or(base.height),
+		unit: unit.unwrap_or(base.unit),
+		dpi: number_setting("dpi", surface, errors).unwrap_or(base.dpi),
+		display_width: number_setting("canvas_display_width", surface, errors).or(base.display_width),
+		min_dpi: number_setting("min_dpi", surface, errors).unwrap_or(base.min_dpi),
+		bleed_mm: number_setting("bleed_mm", surface, errors).unwrap_or(base.bleed_mm),
+		safe_mm: number_setting("safe_mm", surface, errors).unwrap_or(base.safe_mm),
+		export_bleed: bool_setting("export_bleed", surface, errors).unwrap_or(base.export_bleed),
+		mask_path: mask_path_setting(surface, errors).or_else(|| base.mask_path.clone()),
+		mask_image: get_setting_param("mask_image", surface).or_else(|| base.mask_image.clone()),
+		background_color: get_setting_param("canvas_background_color", surface).or_else(|| base.background_color.clone()),
+		background_image: get_setting_param("canvas_background_image", surface).or_else(|| base.background_image.clone()),
+		transparent_export: bool_setting("transparent_export", surface, errors).unwrap_or(base.transparent_export),
 	};
 
-	CanvasSettings {
-		width,
-		height,
+	if settings.missing_width() {
+		errors.push(QueryError::MissingWidth { unit: settings.unit });
+		settings.unit = base.unit;
+	}
+	settings
+}
+
+// Mask paths go in an SVG attribute, so anything that isn't path data is turned away
+pub fn check_mask_path(path: &str) -> Result<(), String> {
+	for segment in SimplifyingPathParser::from(path) {
+		segment.map_err(|err| format!("Invalid mask path: {}", err))?;
+	}
+	Ok(())
+}
+
+fn mask_path_setting(surface: Option<&str>, errors: &mut Vec<QueryError>) -> Option<String> {
+	let path = get_setting_param("mask_path", surface)?;
+	if check_mask_path(&path).is_err() {
+		errors.push(QueryError::BadValue { param: "mask_path".to_string(), index: None, value: path });
+		return None;
+	}
+	Some(path)
+}
+
+// Quote a URL for CSS, e.g. in background-image. Characters that could end the url() are
+// percent-encoded, so a URL from the query string can't add its own CSS
+pub fn css_url(url: &str) -> String {
+	let mut quoted = String::from("url(\"");
+	for c in url.chars() {
+		match c {
+			'"' | '\'' | '(' | ')' | '\\' | '\n' | '\r' => quoted.push_str(&format!("%{:02X}", c as u32)),
+			_ => quoted.push(c),
+		}
+	}
+	quoted.push_str("\")");
+	quoted
+}
+
+// Get the design given as a design= link (see design_link)
+pub fn parse_design_query(errors: &mut Vec<QueryError>) -> Option<Vec<SurfaceDesign>> {
+	let encoded = get_query_param(DESIGN_PARAM).into_iter().next()?;
+	match decode_design(&encoded) {
+		Ok(design) => Some(design),
+		Err(message) => {
+			errors.push(QueryError::InvalidDesign { message });
+			None
+		}
+	}
+}
+
+// Get the names of the surfaces to design on, if any are given
+pub fn parse_surfaces_query() -> Vec<String> {
+	get_query_param("surfaces")
+}
+
+// Override the save settings in base with any given in the query string
+pub fn parse_save_query(base: &SaveSettings, errors: &mut Vec<QueryError>) -> SaveSettings {
+	SaveSettings {
+		show_button: bool_setting("save_button", None, errors).unwrap_or(base.show_button),
+		download: bool_setting("save_download", None, errors).unwrap_or(base.download),
+	}
+}
+
+// Whether the query string asks for strict mode (see EditorConfig::strict_query)
+pub fn parse_strict_query(errors: &mut Vec<QueryError>) -> Option<bool> {
+	bool_setting("strict_query", None, errors)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn physical_units_need_a_width() {
+		let settings = CanvasSettingsBuilder::default().unit(Unit::Mm).build().unwrap();
+		assert_eq!(settings.validate(), Err("A canvas in mm needs a width".to_string()));
+
+		let settings = CanvasSettingsBuilder::default().unit(Unit::Mm).width(Some(100.0)).build().unwrap();
+		assert_eq!(settings.validate(), Ok(()));
+		assert_eq!(settings.px_per_unit(), DEFAULT_CANVAS_SIZE as f64 / 100.0);
+	}
+
+	// update_settings merges changes into the settings' JSON, so it has to read back the same
+	#[test]
+	fn settings_round_trip_through_json() {
+		let settings = CanvasSettingsBuilder::default()
+			.unit(Unit::Mm)
+			.width(Some(100.0))
+			.mask_path(Some("M 0 0 L 100 0 L 50 80 Z".to_string()))
+			.background_color(Some("#ffffff".to_string()))
+			.build()
+			.unwrap();
+		let json = serde_json::to_value(&settings).unwrap();
+		assert!(serde_json::from_value::<CanvasSettings>(json).unwrap() == settings);
+	}
+
+	#[test]
+	fn mask_paths_have_to_be_path_data() {
+		assert_eq!(check_mask_path("M 0 0 L 100 0 L 50 80 Z"), Ok(()));
+		assert!(check_mask_path("M 0 0 L 100 0\" /><script>alert(1)</script><path d=\"").is_err());
+
+		let settings = CanvasSettingsBuilder::default().mask_path(Some("M 0 0 L 10 0) url(".to_string())).build().unwrap();
+		assert!(settings.validate().is_err());
+		assert_eq!(settings.mask_url(), None);
+	}
+
+	#[test]
+	fn css_urls_cant_end_early() {
+		assert_eq!(css_url("https://example.com/a b.png"), "url(\"https://example.com/a b.png\")");
+		assert_eq!(css_url("x\"); color: red; (\\"), "url(\"x%22%29; color: red; %28%5C\")");
+	}
+
+	#[test]
+	fn pixels_need_no_width() {
+		let settings = CanvasSettingsBuilder::default().build().unwrap();
+		assert_eq!(settings.validate(), Ok(()));
+		assert_eq!(settings.px_per_unit(), 1.0);
 	}
 }
@@ -0,0 +1,80 @@
+// A printable side of the product, e.g. the front or back of a shirt
+// Each surface has its own canvas settings and items
+use serde::{Deserialize, Serialize};
+use web_sys::console;
+use crate::page_items::{PageItems, next_item_id};
+use super::settings::CanvasSettings;
+use super::print_quality::*;
+
+#[derive(PartialEq, Clone)]
+pub struct Surface {
+	pub name: String,
+	pub canvas_settings: CanvasSettings,
+	pub items: Vec<PageItems>,
+	pub print_warnings: Vec<DpiWarning>,
+	pub safe_zone_warnings: Vec<SafeZoneWarning>,
+	// Ids of placeholders the customer hasn't put a photo in yet
+	pub empty_placeholders: Vec<u32>,
+}
+
+// The part of a surface that gets saved
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+pub struct SurfaceDesign {
+	pub name: String,
+	pub items: Vec<PageItems>,
+}
+
+impl Surface {
+	pub fn new(name: String, canvas_settings: CanvasSettings) -> Self {
+		Self {
+			name,
+			canvas_settings,
+			items: Vec::new(),
+			print_warnings: Vec::new(),
+			safe_zone_warnings: Vec::new(),
+			empty_placeholders: Vec::new(),
+		}
+	}
+
+	// Images with a source are saved as just the source, and loaded from it again
+	pub fn design(&self) -> SurfaceDesign {
+		let items = self.items.iter().cloned().map(|mut item| {
+			if let Some(file) = item.file.as_mut().filter(|file| file.source.is_some()) {
+				file.data = Vec::new();
+			}
+			item
+		});
+		SurfaceDesign {
+			name: self.name.clone(),
+			items: items.collect(),
+		}
+	}
+
+	// Recheck how every item will print, and which placeholders are empty. Returns true if any of
+	// the warnings changed
+	pub fn refresh_warnings(&mut self) -> bool {
+		let print_warnings = check_print_quality(&self.items, &self.canvas_settings);
+		let safe_zone_warnings = check_safe_zone(&self.items, &self.canvas_settings);
+		let empty_placeholders: Vec<u32> = self.items.iter().filter(|item| item.is_empty_placeholder()).map(|item| item.id).collect();
+		let changed = print_warnings != self.print_warnings || safe_zone_warnings != self.safe_zone_warnings || empty_placeholders != self.empty_placeholders;
+
+		self.print_warnings = print_warnings;
+		self.safe_zone_warnings = safe_zone_warnings;
+		self.empty_placeholders = empty_placeholders;
+		changed
+	}
+}
+
+// Add the items of a saved design or template to the surfaces with the same names
+pub fn add_design(surfaces: &mut [Surface], designs: &[SurfaceDesign]) {
+	for design in designs {
+		match surfaces.iter_mut().find(|surface| surface.name == design.name) {
+			// Designs can be loaded more than once, so the items get ids that aren't in use
+			Some(surface) => surface.items.extend(design.items.iter().cloned().map(|mut item| {
+				item.id = next_item_id();
+				item
+			})),
+			None => console::error_1(&format!("No surface called {}. Items not added", design.name).into()),
+		}
+	}
+}
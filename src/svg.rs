@@ -0,0 +1,283 @@
+// Writes a surface as an SVG, for engraving and cutting machines
+// Everything is positioned in canvas units, so the SVG has the canvas' physical size. Text stays as
+// <text>, and images are embedded as data URIs so the file works on its own. The background and mask
+// images are given as bytes, keyed by their URL. This only builds a string, so the output is the same
+// wherever it's made, and is checked against the files in tests/golden
+use std::collections::HashMap;
+use std::fmt::Write;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use crate::page_items::PageItems;
+use crate::text_details::TextDetails;
+use crate::file_details::FileDetails;
+use super::print_quality::item_area;
+use super::settings::{Area, CanvasSettings, Unit};
+
+pub fn export_svg(settings: &CanvasSettings, items: &[PageItems], images: &HashMap<String, Vec<u8>>) -> String {
+	// Cut the bleed off if it shouldn't be exported
+	let view_box = if settings.export_bleed {
+		Area {
+			x: 0.0,
+			y: 0.0,
+			width: settings.full_width_units(),
+			height: settings.full_height_units(),
+		}
+	} else {
+		settings.trim_area()
+	};
+
+	let unit = match settings.unit {
+		Unit::Px => "",
+		Unit::Mm => "mm",
+		Unit::In => "in",
+	};
+
+	let mut svg = String::new();
+	let _ = writeln!(
+		svg,
+		r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}{unit}" height="{}{unit}" viewBox="{} {} {} {}">"#,
+		view_box.width, view_box.height, view_box.x, view_box.y, view_box.width, view_box.height,
+	);
+
+	let full_area = Area {
+		x: 0.0,
+		y: 0.0,
+		width: settings.full_width_units(),
+		height: settings.full_height_units(),
+	};
+
+	// Everything is drawn inside a group, so the mask covers the background as well
+	if let Some(path) = &settings.mask_path {
+		let _ = writeln!(svg, r#"<clipPath id="mask"><path d="{}" /></clipPath>"#, escape(path));
+		svg.push_str("<g clip-path=\"url(#mask)\">\n");
+	} else if let Some(image) = &settings.mask_image {
+		let _ = writeln!(
+			svg,
+			r#"<mask id="mask" style="mask-type: alpha">{}</mask>"#,
+			image_element(&embedded(image, images), &full_area, "none"),
+		);
+		svg.push_str("<g mask=\"url(#mask)\">\n");
+	} else {
+		svg.push_str("<g>\n");
+	}
+
+	if !settings.transparent_export {
+		if let Some(color) = &settings.background_color {
+			let _ = writeln!(svg, "{}", rect_element(&full_area, color));
+		}
+		if let Some(image) = &settings.background_image {
+			// Cover the whole canvas, keeping the image centered
+			let _ = writeln!(svg, "{}", image_element(&embedded(image, images), &full_area, "xMidYMid slice"));
+		}
+	}
+
+	// The first item is drawn on top, same as on the page
+	for (index, item) in items.iter().enumerate().rev() {
+		let area = item_area(item, settings);
+		if let Some(file) = &item.file {
+			if item.fills_frame() && file.width > 0 && file.height > 0 {
+				svg.push_str(&framed_image_element(index, file, item, &area));
+			} else {
+				let _ = writeln!(svg, "{}", image_element(&data_uri(file), &area, "none"));
+			}
+		} else if let Some(text) = &item.text {
+			let font_size = settings.from_screen(text.font_size as i32);
+			svg.push_str(&text_element(index, text, &area, font_size));
+		}
+	}
+
+	svg.push_str("</g>\n</svg>\n");
+	svg
+}
+
+// Draw text the same way the text components show it: a filled box with the text in the top left
+fn text_element(index: usize, text: &TextDetails, area: &Area, font_size: f64) -> String {
+	let mut svg = String::new();
+	let clip_id = format!("text-{}", index);
+
+	let _ = writeln!(
+		svg,
+		r#"<clipPath id="{}"><rect x="{}" y="{}" width="{}" height="{}" /></clipPath>"#,
+		clip_id, area.x, area.y, area.width, area.height,
+	);
+	let _ = writeln!(svg, r#"<g clip-path="url(#{})">"#, clip_id);
+	let _ = writeln!(svg, "{}", rect_element(area, &text.background_color));
+	let _ = writeln!(
+		svg,
+		r#"<text font-family="{}" font-size="{}" fill="{}" xml:space="preserve">"#,
+		escape(&text.font_family), font_size, escape(&text.font_color),
+	);
+
+	// Lines are spaced the same as the image exports, with the baseline most of the way down the line
+	for (i, line) in text.text.lines().enumerate() {
+		let baseline = area.y + font_size * (0.8 + 1.2 * i as f64);
+		let _ = writeln!(svg, r#"<tspan x="{}" y="{}">{}</tspan>"#, area.x, baseline, escape(line));
+	}
+
+	svg.push_str("</text>\n</g>\n");
+	svg
+}
+
+// Draw a placeholder's image the way it's cropped on the page, cut off at the edges of the frame
+fn framed_image_element(index: usize, file: &FileDetails, item: &PageItems, area: &Area) -> String {
+	let mut svg = String::new();
+	let clip_id = format!("frame-{}", index);
+	let (x, y, width, height) = item.crop.layout(area.width, area.height, file.width as f64, file.height as f64);
+	let image_area = Area { x: area.x + x, y: area.y + y, width, height };
+
+	let _ = writeln!(
+		svg,
+		r#"<clipPath id="{}"><rect x="{}" y="{}" width="{}" height="{}" /></clipPath>"#,
+		clip_id, area.x, area.y, area.width, area.height,
+	);
+	let _ = writeln!(svg, r#"<g clip-path="url(#{})">"#, clip_id);
+	let _ = writeln!(svg, "{}", image_element(&data_uri(file), &image_area, "none"));
+	svg.push_str("</g>\n");
+	svg
+}
+
+fn rect_element(area: &Area, fill: &str) -> String {
+	format!(
+		r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" />"#,
+		area.x, area.y, area.width, area.height, escape(fill),
+	)
+}
+
+fn image_element(href: &str, area: &Area, preserve_aspect_ratio: &str) -> String {
+	format!(
+		r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="{}" href="{}" />"#,
+		area.x, area.y, area.width, area.height, preserve_aspect_ratio, escape(href),
+	)
+}
+
+fn data_uri(file: &FileDetails) -> String {
+	format!("data:{};base64,{}", file.file_type, STANDARD.encode(&file.data))
+}
+
+// Embed an image given by URL. Images that weren't loaded are linked to instead
+fn embedded(url: &str, images: &HashMap<String, Vec<u8>>) -> String {
+	let Some(bytes) = images.get(url) else {
+		return url.to_string();
+	};
+
+	let mime_type = match image::guess_format(bytes) {
+		Ok(format) => format.to_mime_type(),
+		// Masks are often SVGs, which the image crate doesn't read
+		Err(_) if bytes.trim_ascii_start().starts_with(b"<") => "image/svg+xml",
+		Err(_) => "application/octet-stream",
+	};
+	format!("data:{};base64,{}", mime_type, STANDARD.encode(bytes))
+}
+
+// Escape text for use in XML content and attributes
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&apos;"),
+			_ => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+	use crate::page_items::{ImageCrop, ItemRole, PageItemsBuilder};
+	use crate::text_details::TextDetailsBuilder;
+	use super::super::settings::CanvasSettingsBuilder;
+	use super::*;
+
+	// The start of a PNG, which is all that's needed to tell what the bytes are
+	const PNG_BYTES: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+	// Compare with the SVG in tests/golden. Run with UPDATE_GOLDEN=1 to write the files instead
+	fn assert_golden(name: &str, svg: &str) {
+		let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.svg", name));
+		if std::env::var_os("UPDATE_GOLDEN").is_some() {
+			std::fs::write(&path, svg).unwrap();
+			return;
+		}
+
+		let expected = std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("Couldn't read {}: {}", path.display(), err));
+		assert_eq!(svg, expected, "{} doesn't match {}", name, path.display());
+	}
+
+	fn file(width: i32, height: i32) -> FileDetails {
+		FileDetails {
+			name: "photo.png".to_string(),
+			file_type: "image/png".to_string(),
+			data: PNG_BYTES.to_vec(),
+			width,
+			height,
+			source: None,
+		}
+	}
+
+	#[test]
+	fn text() {
+		let settings = CanvasSettingsBuilder::default().width(Some(200.0)).height(Some(100.0)).build().unwrap();
+		let text = TextDetailsBuilder::default()
+			.text("Fish & Chips\n<Tuesday>".to_string())
+			.font_size(16)
+			.font_family("Open Sans".to_string())
+			.font_color("#ff0000".to_string())
+			.build().unwrap();
+		let items = vec![PageItemsBuilder::default().id(1).text(Some(text)).x(10.0).y(20.0).width(Some(120.0)).height(Some(40.0)).build().unwrap()];
+
+		assert_golden("text", &export_svg(&settings, &items, &HashMap::new()));
+	}
+
+	#[test]
+	fn image() {
+		let settings = CanvasSettingsBuilder::default()
+			.width(Some(200.0))
+			.height(Some(100.0))
+			.background_color(Some("#eeeeee".to_string()))
+			.background_image(Some("https://example.com/background.png".to_string()))
+			.build().unwrap();
+		let items = vec![PageItemsBuilder::default().id(1).file(Some(file(40, 20))).x(5.0).y(5.0).width(Some(80.0)).build().unwrap()];
+		let images = HashMap::from([("https://example.com/background.png".to_string(), PNG_BYTES.to_vec())]);
+
+		let svg = export_svg(&settings, &items, &images);
+		assert!(!svg.contains("https://"), "The background should be embedded");
+		assert_golden("image", &svg);
+	}
+
+	#[test]
+	fn mask_path() {
+		let settings = CanvasSettingsBuilder::default()
+			.width(Some(100.0))
+			.height(Some(50.0))
+			.unit(Unit::Mm)
+			.bleed_mm(3.0)
+			.export_bleed(false)
+			.mask_path(Some("M 3 3 L 103 3 L 53 53 Z".to_string()))
+			.background_color(Some("blue".to_string()))
+			.build().unwrap();
+
+		assert_golden("mask_path", &export_svg(&settings, &[], &HashMap::new()));
+	}
+
+	#[test]
+	fn placeholder_crop() {
+		let settings = CanvasSettingsBuilder::default().width(Some(200.0)).height(Some(200.0)).build().unwrap();
+		let items = vec![PageItemsBuilder::default()
+			.id(1)
+			.file(Some(file(400, 200)))
+			.role(ItemRole::Placeholder)
+			.crop(ImageCrop { zoom: 2.0, position_x: 0.0, position_y: 0.5 })
+			.x(50.0)
+			.y(50.0)
+			.width(Some(100.0))
+			.height(Some(100.0))
+			.build().unwrap()];
+
+		assert_golden("placeholder_crop", &export_svg(&settings, &items, &HashMap::new()));
+	}
+}
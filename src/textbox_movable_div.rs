@@ -4,7 +4,7 @@ mod generic_movable_div;
 use yew::prelude::*;
 use crate::text_details::TextDetails;
 use generic_movable_div::MouseMoveComponent;
-use web_sys::HtmlInputElement;
+use web_sys::{HtmlElement, HtmlInputElement};
 
 #[derive(PartialEq, Properties)]
 pub struct MovableTextProps {
@@ -24,6 +24,16 @@ pub struct MovableTextProps {
 	pub start_x: Option<i32>,
 	#[prop_or(None)]
 	pub start_y: Option<i32>,
+	#[prop_or_default]
+	pub ondelete: Callback<()>,
+	#[prop_or_default]
+	pub onchange: Callback<(i32, i32, i32, i32)>,
+	#[prop_or_default]
+	pub onselect: Callback<()>,
+	#[prop_or_default]
+	pub ontextchange: Callback<String>,
+	#[prop_or(false)]
+	pub selected: bool,
 	pub text: TextDetails,
 }
 
@@ -36,17 +46,57 @@ pub fn MovableTextComponent(props: &MovableTextProps) -> Html {
 	let height = props.height.clone();
 	let start_x = props.start_x.clone();
 	let start_y = props.start_y.clone();
+	let ondelete = props.ondelete.clone();
+	let onchange = props.onchange.clone();
+	let onselect = props.onselect.clone();
+	let selected_item = props.selected;
 
-	let text_details = use_state(|| props.text.clone());
+	// Follows the props, so changes from the host page show up. The text itself is kept while it's edited
+	let text_details = &props.text;
 	let text = use_state(|| props.text.text.clone());
 
 	let selected = use_state(|| false);
+	// Started with Enter, and stopped when the text field loses focus
+	let editing = use_state(|| false);
+	let textarea_node_ref = use_node_ref();
+
+	{
+		let text = text.clone();
+		let textarea_node_ref = textarea_node_ref.clone();
+		use_effect_with(props.text.text.clone(), move |new_text| {
+			let focused = textarea_node_ref.cast::<HtmlElement>().is_some_and(|textarea| textarea.matches(":focus").unwrap_or(false));
+			if !focused {
+				text.set(new_text.clone());
+			}
+		});
+	}
+
+	{
+		let textarea_node_ref = textarea_node_ref.clone();
+		use_effect_with(*editing, move |editing| {
+			if let Some(textarea) = textarea_node_ref.cast::<HtmlElement>().filter(|_| *editing) {
+				let _ = textarea.focus();
+			}
+		});
+	}
+
+	let onedit = {
+		let editing = editing.clone();
+		move |_| editing.set(true)
+	};
+
+	let onblur = {
+		let editing = editing.clone();
+		move |_: FocusEvent| editing.set(false)
+	};
 
 	let oninput = {
 		let text = text.clone();
+		let ontextchange = props.ontextchange.clone();
 		move |e: InputEvent| {
 			let input: HtmlInputElement = e.target_unchecked_into();
 			text.set(input.value());
+			ontextchange.emit(input.value());
 		}
 	};
 
@@ -72,10 +122,10 @@ pub fn MovableTextComponent(props: &MovableTextProps) -> Html {
 	);
 
 	html! {
-		<MouseMoveComponent {id} {class} {style} {width} {height} {start_x} {start_y}>
-			if *selected && (*text_details).editable {
-				<textarea value={ text.to_string() } style={format!("resize: none; overflow: hidden; width: 98%; height: 98%;{}", font_style)}
-				{oninput} {onmouseenter} {onmouseleave} />
+		<MouseMoveComponent {id} {class} {style} {width} {height} {start_x} {start_y} {ondelete} {onchange} {onselect} {onedit} selected={selected_item}>
+			if (*selected || *editing) && text_details.editable {
+				<textarea ref={textarea_node_ref} value={ text.to_string() } style={format!("resize: none; overflow: hidden; width: 98%; height: 98%;{}", font_style)}
+				{oninput} {onmouseenter} {onmouseleave} {onblur} />
 			} else {
 				<div style={format!("width: 100%; height: 100%; background-color: {}", text_details.background_color)} {onmouseenter} {onmouseleave}>
 					<span style={font_style}> { text.to_string() } </span>
@@ -1,6 +1,10 @@
+// Text that can't be moved. If it's editable it can be selected with the mouse or Tab, and Enter
+// starts editing it
 use yew::prelude::*;
 use crate::text_details::TextDetails;
-use web_sys::HtmlInputElement;
+use web_sys::{HtmlElement, HtmlInputElement};
+use super::clipboard::in_text_field;
+use super::selection_focus::use_selection_focus;
 
 #[derive(PartialEq, Properties)]
 pub struct StaticTextProps {
@@ -20,6 +24,13 @@ pub struct StaticTextProps {
 	pub x: Option<i32>,
 	#[prop_or(None)]
 	pub y: Option<i32>,
+	#[prop_or_default]
+	pub ontextchange: Callback<String>,
+	#[prop_or_default]
+	pub onselect: Callback<()>,
+	// Editable text takes focus when it's selected, so it can be used with the keyboard
+	#[prop_or(false)]
+	pub selected: bool,
 	pub text: TextDetails,
 }
 
@@ -33,16 +44,90 @@ pub fn static_text_component(props: &StaticTextProps) -> Html {
 	let left = props.x.clone();
 	let top = props.y.clone();
 
-	let text_details = use_state(|| props.text.clone());
+	// Follows the props, so changes from the host page show up. The text itself is kept while it's edited
+	let text_details = &props.text;
 	let text = use_state(|| props.text.text.clone());
+	let textarea_node_ref = use_node_ref();
+
+	{
+		let text = text.clone();
+		let textarea_node_ref = textarea_node_ref.clone();
+		use_effect_with(props.text.text.clone(), move |new_text| {
+			let focused = textarea_node_ref.cast::<HtmlElement>().is_some_and(|textarea| textarea.matches(":focus").unwrap_or(false));
+			if !focused {
+				text.set(new_text.clone());
+			}
+		});
+	}
 
 	let selected = use_state(|| false);
+	// Started with Enter, and stopped when the text field loses focus
+	let editing = use_state(|| false);
+	let div_node_ref = use_node_ref();
+
+	use_selection_focus(div_node_ref.clone(), props.selected && text_details.editable);
+
+	{
+		let textarea_node_ref = textarea_node_ref.clone();
+		use_effect_with(*editing, move |editing| {
+			if let Some(textarea) = textarea_node_ref.cast::<HtmlElement>().filter(|_| *editing) {
+				let _ = textarea.focus();
+			}
+		});
+	}
+
+	let onkeydown = {
+		let editing = editing.clone();
+		let div_node_ref = div_node_ref.clone();
+		let editable = text_details.editable;
+		move |event: KeyboardEvent| {
+			if !editable {
+				return;
+			}
+			// Escape stops editing, without deselecting the text
+			if in_text_field(event.target()) {
+				if event.key() == "Escape" {
+					event.stop_propagation();
+					if let Some(element) = div_node_ref.cast::<HtmlElement>() {
+						let _ = element.focus();
+					}
+				}
+				return;
+			}
+			if event.key() == "Enter" {
+				event.prevent_default();
+				editing.set(true);
+			}
+		}
+	};
+
+	let onblur = {
+		let editing = editing.clone();
+		move |_: FocusEvent| editing.set(false)
+	};
+
+	let onselect = {
+		let onselect = props.onselect.clone();
+		let editable = text_details.editable;
+		move || {
+			if editable {
+				onselect.emit(());
+			}
+		}
+	};
+	let onfocus = {
+		let onselect = onselect.clone();
+		move |_: FocusEvent| onselect()
+	};
+	let onmousedown = move |_: MouseEvent| onselect();
 
 	let oninput = {
 		let text = text.clone();
+		let ontextchange = props.ontextchange.clone();
 		move |e: InputEvent| {
 			let input: HtmlInputElement = e.target_unchecked_into();
 			text.set(input.value());
+			ontextchange.emit(input.value());
 		}
 	};
 
@@ -76,10 +161,10 @@ pub fn static_text_component(props: &StaticTextProps) -> Html {
 	);
 
 	html! {
-		<div {id} {class} {style}>
-			if *selected && (*text_details).editable {
-				<textarea value={ text.to_string() } style={format!("resize: none; overflow: hidden; width: 98%; height: 98%;{}", font_style)}
-				{oninput} {onmouseenter} {onmouseleave} />
+		<div ref={div_node_ref} {id} {class} {style} tabindex={text_details.editable.then_some("0")} {onkeydown} {onfocus} {onmousedown}>
+			if (*selected || *editing) && text_details.editable {
+				<textarea ref={textarea_node_ref} value={ text.to_string() } style={format!("resize: none; overflow: hidden; width: 98%; height: 98%;{}", font_style)}
+				{oninput} {onmouseenter} {onmouseleave} {onblur} />
 			} else {
 				<div style={format!("width: 100%; height: 100%; background-color: {}; overflow: hidden", text_details.background_color)} {onmouseenter} {onmouseleave}>
 					<span style={font_style}> { text.to_string() } </span>